@@ -0,0 +1,75 @@
+//! Speed of the core of the game, run with `cargo bench`. Each benchmark
+//! prints its time per iteration and iterations per second.
+
+use connect_four::board_game::{Game, PlayResult};
+
+use rand::seq::IteratorRandom;
+use std::hint::black_box;
+use std::time::{Duration, Instant};
+
+/// Time spent measuring each benchmark.
+const BUDGET: Duration = Duration::from_secs(2);
+
+fn main() {
+    let mut middle = Game::default();
+    middle.play_notation("4433252166").unwrap();
+
+    let mut full = Game::default();
+    full.play_notation("111111222222333333544444455555666666").unwrap();
+
+    bench("play_col", || {
+        let mut game = middle;
+        black_box(game.play_col(black_box(4)));
+    });
+
+    bench("connected", || {
+        for col in 0..Game::COL {
+            for row in 0..Game::ROW {
+                black_box(full.connected(col, row));
+            }
+        }
+    });
+
+    bench("col_height", || {
+        for col in 0..Game::COL {
+            black_box(middle.col_height(black_box(col)));
+        }
+    });
+
+    let mut rng = rand::thread_rng();
+    bench("random playout", || {
+        let mut game = Game::default();
+
+        while let Some(code) = game.legal_moves().choose(&mut rng) {
+            if !matches!(game.play(code), PlayResult::Pass) {
+                break;
+            }
+        }
+
+        black_box(game);
+    });
+
+    bench("perft 5", || {
+        black_box(Game::default().perft(5));
+    });
+}
+
+/// Run `iteration` for `BUDGET`, in batches growing until one is long
+/// enough to time.
+fn bench(name: &str, mut iteration: impl FnMut()) {
+    let start = Instant::now();
+    let mut iterations = 0u64;
+    let mut batch = 1;
+
+    while start.elapsed() < BUDGET {
+        for _ in 0..batch {
+            iteration();
+        }
+
+        iterations += batch;
+        batch *= 2;
+    }
+
+    let per_iteration = start.elapsed().as_secs_f64() / iterations as f64;
+    println!("{name:<16} {:>12.1} ns/iter {:>14.0} iter/s", per_iteration * 1e9, 1.0 / per_iteration);
+}
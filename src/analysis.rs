@@ -0,0 +1,168 @@
+//! Threat analysis after Allis: the empty cells completing a line of a
+//! player, the parity of their row and the player they win for once the
+//! grid fills up.
+//!
+//! Rows are counted from 1 at the bottom. When the two players fill the
+//! columns in turn, the player to move on an even number of coins gets
+//! the odd rows and the other one the even rows, so an odd threat is
+//! worth most to the former and an even threat to the latter.
+
+use crate::board_game::{Game, Player};
+use crate::search::MATE;
+
+/// Score of a useful threat, see `evaluate`.
+const THREAT: i16 = 10;
+/// Score of winning by zugzwang, see `evaluate`.
+const ZUGZWANG: i16 = 100;
+
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub enum Parity {
+    Odd, Even,
+}
+
+/// Empty cell where a coin of `player` would complete a line.
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub struct Threat {
+    pub player: Player,
+    pub col: usize,
+    pub row: usize,
+}
+
+impl Threat {
+    pub fn parity(&self) -> Parity {
+        if self.row.is_multiple_of(2) { Parity::Odd } else { Parity::Even }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Analysis {
+    threats: Vec<Threat>,
+    /// Players getting the odd and the even rows, in a two player game.
+    parities: Option<(Player, Player)>,
+}
+
+impl Analysis {
+    pub fn new(game: &Game) -> Self {
+        let threats = (0..game.cols())
+            .flat_map(|col| (game.col_height(col)..game.rows()).map(move |row| (col, row)))
+            .flat_map(|(col, row)| game.players().iter().map(move |&player| Threat { player, col, row }))
+            .filter(|threat| game.completes_line(threat.col, threat.row, threat.player))
+            .collect();
+
+        let coins = game.grid().iter().flatten().count();
+        let parities = game.opponent_of(game.player_turn()).map(|other| match coins % 2 {
+            0 => (game.player_turn(), other),
+            _ => (other, game.player_turn()),
+        });
+
+        Self { threats, parities }
+    }
+
+    /// Threats of every player, column after column from the bottom up.
+    pub fn threats(&self) -> &[Threat] {
+        &self.threats
+    }
+
+    pub fn threats_of(&self, player: Player) -> impl Iterator<Item = &Threat> {
+        self.threats.iter().filter(move |threat| threat.player == player)
+    }
+
+    /// No threat of another player lies below `threat` in its column,
+    /// that player would win there first.
+    pub fn useful(&self, threat: &Threat) -> bool {
+        !self.threats.iter().any(|other| other.col == threat.col && other.row < threat.row && other.player != threat.player)
+    }
+
+    /// Player getting the rows of `parity` when the grid fills up, in a
+    /// two player game.
+    pub fn player_of(&self, parity: Parity) -> Option<Player> {
+        self.parities.map(|(odd, even)| match parity {
+            Parity::Odd => odd,
+            Parity::Even => even,
+        })
+    }
+
+    /// Player winning by zugzwang when the grid fills up: the odd player
+    /// with a useful odd threat, or else the even player with a useful
+    /// even threat. An odd threat beats an even one in another column.
+    pub fn zugzwang(&self) -> Option<Player> {
+        [Parity::Odd, Parity::Even].into_iter().find_map(|parity| {
+            let player = self.player_of(parity)?;
+
+            self.threats_of(player)
+                .any(|threat| threat.parity() == parity && self.useful(threat))
+                .then_some(player)
+        })
+    }
+}
+
+/// Heuristic score of the position for the player to move, short of the
+/// forced wins of `search`: useful threats of the player less those of
+/// the others, winning by zugzwang counting most.
+pub fn evaluate(game: &Game) -> i16 {
+    let analysis = Analysis::new(game);
+    let player = game.player_turn();
+    let side = |owner: Player| if owner == player { 1 } else { -1 };
+
+    let threats = analysis.threats().iter()
+        .filter(|threat| analysis.useful(threat))
+        .map(|threat| THREAT * side(threat.player))
+        .sum::<i16>();
+
+    let zugzwang = analysis.zugzwang().map_or(0, |owner| ZUGZWANG * side(owner));
+
+    (threats + zugzwang).clamp(1 - MATE, MATE - 1)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn played(moves: &str) -> Game {
+        let mut game = Game::default();
+        game.play_notation(moves).unwrap();
+        game
+    }
+
+    #[test]
+    fn threats_are_sorted_by_parity() {
+        // red 2 3 4 on the bottom row, yellow above them
+        let analysis = Analysis::new(&played("223344"));
+
+        let red = analysis.threats_of(Player::Red).collect::<Vec<_>>();
+        assert_eq!(red, [&Threat { player: Player::Red, col: 0, row: 0 }, &Threat { player: Player::Red, col: 4, row: 0 }]);
+        assert!(red.iter().all(|threat| threat.parity() == Parity::Odd));
+
+        let yellow = analysis.threats_of(Player::Yellow).collect::<Vec<_>>();
+        assert_eq!(yellow.iter().map(|threat| (threat.col, threat.row)).collect::<Vec<_>>(), [(0, 1), (4, 1)]);
+        assert!(yellow.iter().all(|threat| threat.parity() == Parity::Even && !analysis.useful(threat)));
+
+        assert_eq!(analysis.player_of(Parity::Odd), Some(Player::Red));
+        assert_eq!(analysis.zugzwang(), Some(Player::Red));
+        assert!(evaluate(&played("223344")) > 0);
+    }
+
+    #[test]
+    fn an_even_threat_wins_without_odd_threats() {
+        // yellow 2 3 4 on the second row, red without a line
+        let game = played("23427473");
+        let analysis = Analysis::new(&game);
+
+        assert_eq!(analysis.threats_of(Player::Red).count(), 0);
+        assert!(analysis.threats().iter().all(|threat| threat.parity() == Parity::Even && analysis.useful(threat)));
+        assert_eq!(analysis.player_of(Parity::Even), Some(Player::Yellow));
+        assert_eq!(analysis.zugzwang(), Some(Player::Yellow));
+        assert!(evaluate(&game) < 0);
+    }
+
+    #[test]
+    fn nobody_has_odd_and_even_rows_with_more_players() {
+        let mut game = Game::new(Player::Red).seating(&[Player::Red, Player::Green, Player::Yellow]);
+        game.play_notation("1112223").unwrap();
+
+        let analysis = Analysis::new(&game);
+        assert!(analysis.threats_of(Player::Red).any(|threat| (threat.col, threat.row) == (3, 0)));
+        assert_eq!(analysis.player_of(Parity::Odd), None);
+        assert_eq!(analysis.zugzwang(), None);
+    }
+}
@@ -0,0 +1,227 @@
+use iced::Point;
+
+/// Map the animation progress `x` in `[0, 1]` to the eased progress.
+/// Every easing starts at 0 and ends at 1, some overshoot in between.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Easing {
+    Linear,
+    EaseIn,
+    EaseOut,
+    EaseInOut,
+    BounceOut,
+    ElasticOut,
+    BackOut,
+    Bezier(Point, Point),
+}
+
+impl Easing {
+    pub fn apply(&self, x: f32) -> f32 {
+        if x <= 0.0 { return 0.0; }
+        if x >= 1.0 { return 1.0; }
+
+        match *self {
+            Easing::Linear => x,
+            Easing::EaseIn => x * x * x,
+            Easing::EaseOut => 1.0 - (1.0 - x).powi(3),
+            Easing::EaseInOut => {
+                if x < 0.5 {
+                    4.0 * x * x * x
+                } else {
+                    1.0 - (2.0 - 2.0 * x).powi(3) / 2.0
+                }
+            }
+            Easing::BounceOut => bounce_out(x),
+            Easing::ElasticOut => {
+                const C4: f32 = std::f32::consts::TAU / 3.0;
+                2f32.powf(-10.0 * x) * ((10.0 * x - 0.75) * C4).sin() + 1.0
+            }
+            Easing::BackOut => {
+                const C1: f32 = 1.70158;
+                const C3: f32 = C1 + 1.0;
+
+                1.0 + C3 * (x - 1.0).powi(3) + C1 * (x - 1.0).powi(2)
+            }
+            Easing::Bezier(ctrl1, ctrl2) => {
+                let t = solve_t(ctrl1.x, ctrl2.x, x);
+                bezier(ctrl1.y, ctrl2.y, t)
+            }
+        }
+    }
+}
+
+fn bounce_out(x: f32) -> f32 {
+    const N1: f32 = 7.5625;
+    const D1: f32 = 2.75;
+
+    if x < 1.0 / D1 {
+        N1 * x * x
+    } else if x < 2.0 / D1 {
+        let x = x - 1.5 / D1;
+        N1 * x * x + 0.75
+    } else if x < 2.5 / D1 {
+        let x = x - 2.25 / D1;
+        N1 * x * x + 0.9375
+    } else {
+        let x = x - 2.625 / D1;
+        N1 * x * x + 0.984375
+    }
+}
+
+/// Find the curve parameter `t` such that `x(t) == x`. Newton–Raphson
+/// converges in a few steps on most curves, bisection takes over when
+/// the slope vanishes or an iterate leaves `[0, 1]`.
+fn solve_t(x1: f32, x2: f32, x: f32) -> f32 {
+    const EPSILON: f32 = 1e-6;
+
+    let mut t = x;
+    for _ in 0..8 {
+        let error = bezier(x1, x2, t) - x;
+        if error.abs() < EPSILON { return t; }
+
+        let slope = bezier_slope(x1, x2, t);
+        if slope.abs() < EPSILON { break; }
+
+        t -= error / slope;
+        if !(0.0..=1.0).contains(&t) { break; }
+    }
+
+    // x(0) = 0 and x(1) = 1, so [0, 1] always bracket a solution
+    let (mut low, mut high) = (0.0, 1.0);
+    t = x;
+
+    while high - low > EPSILON {
+        if bezier(x1, x2, t) < x {
+            low = t;
+        } else {
+            high = t;
+        }
+
+        t = (low + high) / 2.0;
+    }
+
+    t
+}
+
+/// One axis of a cubic bezier going from 0 to 1 through `p1` and `p2`.
+fn bezier(p1: f32, p2: f32, t: f32) -> f32 {
+    let a = 3.0*p1 - 3.0*p2 + 1.0;
+    let b = 3.0*p2 - 6.0*p1;
+    let c = 3.0*p1;
+
+    ((a*t + b)*t + c)*t
+}
+
+fn bezier_slope(p1: f32, p2: f32, t: f32) -> f32 {
+    let a = 3.0*p1 - 3.0*p2 + 1.0;
+    let b = 3.0*p2 - 6.0*p1;
+    let c = 3.0*p1;
+
+    (3.0*a*t + 2.0*b)*t + c
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::{rngs::StdRng, Rng, SeedableRng};
+
+    const SAMPLES: usize = 20_000;
+
+    /// Densely sampled curve computed in f64, used as ground truth.
+    fn reference(ctrl1: Point, ctrl2: Point) -> Vec<(f64, f64)> {
+        let axis = |p1: f32, p2: f32, t: f64| {
+            let (p1, p2) = (p1 as f64, p2 as f64);
+            let u = 1.0 - t;
+
+            3.0*u*u*t*p1 + 3.0*u*t*t*p2 + t*t*t
+        };
+
+        (0..=SAMPLES)
+            .map(|i| i as f64 / SAMPLES as f64)
+            .map(|t| (axis(ctrl1.x, ctrl2.x, t), axis(ctrl1.y, ctrl2.y, t)))
+            .collect()
+    }
+
+    fn reference_y(curve: &[(f64, f64)], x: f64) -> f64 {
+        let index = curve.partition_point(|&(cx, _)| cx < x).clamp(1, curve.len() - 1);
+        let ((x0, y0), (x1, y1)) = (curve[index - 1], curve[index]);
+
+        if x1 - x0 <= f64::EPSILON {
+            y1
+        } else {
+            y0 + (y1 - y0) * (x - x0) / (x1 - x0)
+        }
+    }
+
+    /// First point where the curve leaves the reference, if any.
+    fn curve_mismatch(ctrl1: Point, ctrl2: Point) -> Option<String> {
+        let easing = Easing::Bezier(ctrl1, ctrl2);
+        let curve = reference(ctrl1, ctrl2);
+
+        for i in 0..=200 {
+            let x = i as f32 / 200.0;
+
+            let t = solve_t(ctrl1.x, ctrl2.x, x);
+            if !(0.0..=1.0).contains(&t) {
+                return Some(format!("t = {t} out of range for x = {x}, {ctrl1:?} {ctrl2:?}"));
+            }
+
+            let y = easing.apply(x) as f64;
+            let expected = reference_y(&curve, x as f64);
+
+            if (y - expected).abs() >= 5e-3 {
+                return Some(format!("y({x}) = {y}, expected {expected} for {ctrl1:?} {ctrl2:?}"));
+            }
+        }
+
+        None
+    }
+
+    fn check_curve(ctrl1: Point, ctrl2: Point) {
+        let mismatch = curve_mismatch(ctrl1, ctrl2);
+        assert!(mismatch.is_none(), "{}", mismatch.unwrap_or_default());
+    }
+
+    #[test]
+    fn degenerate_curves() {
+        // linear, zero cubic coefficient and repeated roots
+        check_curve(Point::new(0.0, 0.0), Point::new(1.0, 1.0));
+        check_curve(Point::new(0.5, 0.0), Point::new(0.5, 1.0));
+        check_curve(Point::new(1.0 / 3.0, 1.0 / 3.0), Point::new(2.0 / 3.0, 2.0 / 3.0));
+        check_curve(Point::new(0.0, 1.0), Point::new(1.0, 0.0));
+        check_curve(Point::new(1.0, 0.0), Point::new(0.0, 1.0));
+        check_curve(Point::new(0.65, 0.0), Point::new(0.75, 0.5));
+        check_curve(Point::new(0.4, 0.4), Point::new(0.5, 1.0));
+    }
+
+    /// The same curves on each run, `CURVE_SEED` trying other ones.
+    #[test]
+    fn random_curves() {
+        let seed = std::env::var("CURVE_SEED").ok()
+            .and_then(|seed| seed.parse().ok())
+            .unwrap_or(0x5eed);
+        let mut rand = StdRng::seed_from_u64(seed);
+
+        for _ in 0..200 {
+            let ctrl1 = Point::new(rand.gen_range(0.0..=1.0), rand.gen_range(-1.0..=2.0));
+            let ctrl2 = Point::new(rand.gen_range(0.0..=1.0), rand.gen_range(-1.0..=2.0));
+
+            let mismatch = curve_mismatch(ctrl1, ctrl2);
+            assert!(mismatch.is_none(), "{} with CURVE_SEED={seed}", mismatch.unwrap_or_default());
+        }
+    }
+
+    #[test]
+    fn easings_start_and_end_in_place() {
+        let easings = [
+            Easing::Linear, Easing::EaseIn, Easing::EaseOut, Easing::EaseInOut,
+            Easing::BounceOut, Easing::ElasticOut, Easing::BackOut,
+            Easing::Bezier(Point::new(0.5, 0.0), Point::new(0.5, 1.0)),
+        ];
+
+        for easing in easings {
+            assert_eq!(easing.apply(0.0), 0.0, "{easing:?}");
+            assert_eq!(easing.apply(1.0), 1.0, "{easing:?}");
+            assert!((easing.apply(0.999) - 1.0).abs() < 0.01, "{easing:?}");
+        }
+    }
+}
@@ -0,0 +1,158 @@
+use iced::{Point, Vector};
+use std::time::Instant;
+
+/// Object released without speed that falls `distance` units straight down
+/// and bounces on the floor until its speed drops below `min_speed`.
+/// Distances are in board cells and times in seconds.
+pub struct Fall {
+    start: Point,
+    distance: f32,
+
+    gravity: f32,
+    restitution: f32,
+    min_speed: f32,
+
+    start_animation: Instant,
+}
+
+impl Fall {
+    pub fn new(start: Point, distance: f32) -> Self {
+        Self {
+            start,
+            distance: distance.max(0.0),
+
+            gravity: 40.0,
+            restitution: 0.3,
+            min_speed: 1.5,
+
+            start_animation: Instant::now(),
+        }
+    }
+
+    /// Acceleration in cells per second squared, which must be positive:
+    /// the times of the fall are divided by it.
+    pub fn gravity(mut self, gravity: f32) -> Self {
+        assert!(gravity > 0.0, "gravity must be positive, got {gravity}");
+        self.gravity = gravity;
+        self
+    }
+
+    pub fn restitution(mut self, restitution: f32) -> Self {
+        self.restitution = restitution.clamp(0.0, 0.95);
+        self
+    }
+
+    pub fn restart(&mut self) {
+        self.start_animation = Instant::now();
+    }
+
+    /// Time needed to reach the floor for the first time.
+    pub fn landing_time(&self) -> f32 {
+        (2.0 * self.distance / self.gravity).sqrt()
+    }
+
+    pub fn duration(&self) -> f32 {
+        self.bounces().map(|speed| 2.0 * speed / self.gravity).sum::<f32>() + self.landing_time()
+    }
+
+    pub fn point_at(&self, time: Instant) -> Point {
+        self.point_after(time.saturating_duration_since(self.start_animation).as_secs_f32())
+    }
+
+    pub fn finished_at(&self, time: Instant) -> bool {
+        time.saturating_duration_since(self.start_animation).as_secs_f32() > self.duration()
+    }
+
+    pub fn point_after(&self, elapsed: f32) -> Point {
+        let floor = self.start + Vector::new(0.0, self.distance);
+
+        let landing = self.landing_time();
+        if elapsed < landing {
+            let elapsed = elapsed.max(0.0);
+            return self.start + Vector::new(0.0, 0.5 * self.gravity * elapsed * elapsed);
+        }
+
+        let mut elapsed = elapsed - landing;
+
+        for speed in self.bounces() {
+            let flight = 2.0 * speed / self.gravity;
+
+            if elapsed < flight {
+                let height = speed * elapsed - 0.5 * self.gravity * elapsed * elapsed;
+                return floor - Vector::new(0.0, height);
+            }
+
+            elapsed -= flight;
+        }
+
+        floor
+    }
+
+    /// Upward speed right after each bounce.
+    fn bounces(&self) -> impl Iterator<Item = f32> + '_ {
+        let impact = self.gravity * self.landing_time();
+
+        std::iter::successors(Some(impact * self.restitution), |speed| Some(speed * self.restitution))
+            .take_while(|&speed| speed >= self.min_speed)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn falls_freely_until_landing() {
+        let fall = Fall::new(Point::new(1.0, 0.5), 6.0);
+        let landing = fall.landing_time();
+
+        assert_eq!(fall.point_after(0.0), Point::new(1.0, 0.5));
+        assert!((fall.point_after(landing).y - 6.5).abs() < 1e-4);
+
+        let half = fall.point_after(landing / 2.0).y - 0.5;
+        assert!((half - 1.5).abs() < 1e-4, "a quarter of the height at half the time, got {half}");
+    }
+
+    #[test]
+    #[should_panic(expected = "gravity must be positive")]
+    fn gravity_must_be_positive() {
+        let _ = Fall::new(Point::ORIGIN, 5.0).gravity(0.0);
+    }
+
+    #[test]
+    fn bounces_never_cross_the_floor() {
+        let fall = Fall::new(Point::ORIGIN, 5.0);
+        let bounces = fall.bounces().count();
+        assert!(bounces > 0);
+
+        let steps = 1000;
+        let mut above = false;
+
+        for step in 0..=steps {
+            let y = fall.point_after(fall.duration() * step as f32 / steps as f32).y;
+            assert!(y <= 5.0 + 1e-4, "coin below the floor: {y}");
+
+            above |= step as f32 / steps as f32 * fall.duration() > fall.landing_time() && y < 4.9;
+        }
+
+        assert!(above, "the coin never bounced");
+        assert_eq!(fall.point_after(fall.duration() + 1.0), Point::new(0.0, 5.0));
+    }
+
+    #[test]
+    fn duration_depends_on_height() {
+        let short = Fall::new(Point::ORIGIN, 1.0);
+        let long = Fall::new(Point::ORIGIN, 6.0);
+
+        assert!(short.duration() < long.duration());
+        assert_eq!(Fall::new(Point::ORIGIN, 0.0).duration(), 0.0);
+    }
+
+    #[test]
+    fn without_restitution_it_stops_on_landing() {
+        let fall = Fall::new(Point::ORIGIN, 3.0).restitution(0.0);
+
+        assert_eq!(fall.bounces().count(), 0);
+        assert_eq!(fall.duration(), fall.landing_time());
+    }
+}
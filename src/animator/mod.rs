@@ -1,13 +1,21 @@
+mod easing;
+mod fall;
+mod scheduler;
+mod timeline;
+
+pub use easing::Easing;
+pub use fall::Fall;
+pub use scheduler::{Scheduler, Track};
+pub use timeline::Timeline;
+
 use iced::{Point, Vector};
 use std::time::Instant;
 
-#[repr(packed)]
+#[repr(Rust, packed)]
 pub struct Animation {
     start: Point,
     direction: Vector,
-
-    control_point1: Point,
-    control_point2: Point,
+    easing: Easing,
 
     start_animation: Instant,
     animation_duration: f32,
@@ -20,8 +28,11 @@ impl Animation {
     }
 
     pub fn update_ctrl(&mut self, ctrl_point1: Point, ctrl_point2: Point) {
-        self.control_point1 = ctrl_point1;
-        self.control_point2 = ctrl_point2;
+        self.easing = Easing::Bezier(ctrl_point1, ctrl_point2);
+    }
+
+    pub fn update_easing(&mut self, easing: Easing) {
+        self.easing = easing;
     }
 
     pub fn update_duration(&mut self, duration: f32) {
@@ -38,11 +49,9 @@ impl Animation {
         if elapsed <= 0.0 { return self.start; }
         if elapsed >= self.animation_duration { return self.start + self.direction; }
 
+        // fields of a packed struct are copied out before use
         let x = elapsed / self.animation_duration;
-        let t = self.compute_t(x);
-        let y = self.compute_y(t);
-
-        self.start + self.direction * y
+        self.start + self.direction * { self.easing }.apply(x)
     }
 
     pub fn finished_at(&self, time: Instant) -> bool {
@@ -55,55 +64,12 @@ impl Animation {
         if x <= 0.0 { return self.start; }
         if x >= 1.0 { return self.start + self.direction; }
 
-        let t = self.compute_t(x);
-        let y = self.compute_y(t);
-
-        self.start + self.direction * y
+        self.start + self.direction * { self.easing }.apply(x)
     }
 
     pub fn start_point(&self) -> Point {
         self.start
     }
-
-    fn compute_t(&self, x: f32) -> f32 {
-        let Point { x: x1, .. } = self.control_point1;
-        let Point { x: x2, .. } = self.control_point2;
-
-        let a = 3.0*x1 - 3.0*x2 + 1.0;
-        let b = 3.0*x2 - 6.0*x1;
-        let c = 3.0*x1;
-        let d = -x;
-
-        let p = (3.0*a*c - b*b) / (3.0*a*a);
-        let q = (2.0*b*b*b - 9.0*a*b*c + 27.0*a*a*d) / (27.0*a*a*a);
-        let det = -4.0*p*p*p - 27.0*q*q;
-
-        let z = match det.total_cmp(&0.0) {
-            std::cmp::Ordering::Greater => {
-                2.0 * (-p/3.0).sqrt() * ((1.0/3.0) * (((3.0*q)/(2.0*p)) * (-3.0/p).sqrt()).acos()).cos()
-            },
-            std::cmp::Ordering::Less => {
-                let u = (-q + (-det / 27.0).sqrt()) / 2.0;
-                let v = (-q - (-det / 27.0).sqrt()) / 2.0;
-
-                u.cbrt() + v.cbrt()
-            },
-            std::cmp::Ordering::Equal => todo!(),
-        };
-
-        z - b / (3.0*a)
-    }
-
-    fn compute_y(&self, t: f32) -> f32 {
-        let Point { y: y1, .. } = self.control_point1;
-        let Point { y: y2, .. } = self.control_point2;
-
-        let a = 3.0*y1 - 3.0*y2 + 1.0;
-        let b = 3.0*y2 - 6.0*y1;
-        let c = 3.0*y1;
-
-        t*t*t*a + t*t*b + t*c
-    }
 }
 
 impl Default for Animation {
@@ -112,8 +78,7 @@ impl Default for Animation {
             start: Point::ORIGIN,
             direction: Vector::new(1.0, 0.0),
 
-            control_point1: Point::new(0.5, 0.0),
-            control_point2: Point::new(0.5, 1.0),
+            easing: Easing::Bezier(Point::new(0.5, 0.0), Point::new(0.5, 1.0)),
 
             start_animation: Instant::now(),
             animation_duration: 1.0
@@ -137,6 +102,11 @@ impl Builder {
         self
     }
 
+    pub fn easing(mut self, easing: Easing) -> Self {
+        self.animation.update_easing(easing);
+        self
+    }
+
     pub fn anim_duration(mut self, duration: f32) -> Self {
         self.animation.update_duration(duration);
         self
@@ -147,3 +117,18 @@ impl Builder {
         self.animation
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn bounds_are_exact() {
+        let animation = Builder::default()
+            .move_curve(Point::new(1.0, 2.0), Vector::new(3.0, -1.0))
+            .build();
+
+        assert_eq!(animation.point_with(0.0), Point::new(1.0, 2.0));
+        assert_eq!(animation.point_with(1.0), Point::new(4.0, 1.0));
+    }
+}
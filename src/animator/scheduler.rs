@@ -0,0 +1,177 @@
+use super::{Animation, Fall, Timeline};
+
+use iced::Point;
+use std::{collections::HashMap, hash::Hash, time::Instant};
+
+pub enum Track {
+    Animation(Animation),
+    Timeline(Timeline),
+    Fall(Fall),
+}
+
+impl Track {
+    pub fn point_at(&self, time: Instant) -> Point {
+        match self {
+            Track::Animation(animation) => animation.point_at(time),
+            Track::Timeline(timeline) => timeline.point_at(time),
+            Track::Fall(fall) => fall.point_at(time),
+        }
+    }
+
+    pub fn finished_at(&self, time: Instant) -> bool {
+        match self {
+            Track::Animation(animation) => animation.finished_at(time),
+            Track::Timeline(timeline) => timeline.finished_at(time),
+            Track::Fall(fall) => fall.finished_at(time),
+        }
+    }
+}
+
+impl From<Animation> for Track {
+    fn from(animation: Animation) -> Self {
+        Track::Animation(animation)
+    }
+}
+
+impl From<Timeline> for Track {
+    fn from(timeline: Timeline) -> Self {
+        Track::Timeline(timeline)
+    }
+}
+
+impl From<Fall> for Track {
+    fn from(fall: Fall) -> Self {
+        Track::Fall(fall)
+    }
+}
+
+struct Entry {
+    track: Track,
+    transient: bool,
+}
+
+/// Named animations running side by side. Persistent tracks keep their
+/// final point once finished, transient ones are dropped by `prune`.
+pub struct Scheduler<K> {
+    entries: HashMap<K, Entry>,
+}
+
+impl<K: Eq + Hash> Scheduler<K> {
+    pub fn insert(&mut self, key: K, track: impl Into<Track>) {
+        self.entries.insert(key, Entry { track: track.into(), transient: false });
+    }
+
+    pub fn insert_transient(&mut self, key: K, track: impl Into<Track>) {
+        self.entries.insert(key, Entry { track: track.into(), transient: true });
+    }
+
+    pub fn remove(&mut self, key: &K) {
+        self.entries.remove(key);
+    }
+
+    pub fn contains(&self, key: &K) -> bool {
+        self.entries.contains_key(key)
+    }
+
+    pub fn animation_mut(&mut self, key: &K) -> Option<&mut Animation> {
+        match self.entries.get_mut(key) {
+            Some(Entry { track: Track::Animation(animation), .. }) => Some(animation),
+            _ => None,
+        }
+    }
+
+    pub fn timeline_mut(&mut self, key: &K) -> Option<&mut Timeline> {
+        match self.entries.get_mut(key) {
+            Some(Entry { track: Track::Timeline(timeline), .. }) => Some(timeline),
+            _ => None,
+        }
+    }
+
+    pub fn point_at(&self, key: &K, time: Instant) -> Option<Point> {
+        self.entries.get(key).map(|entry| entry.track.point_at(time))
+    }
+
+    /// A missing track is considered finished.
+    pub fn finished_at(&self, key: &K, time: Instant) -> bool {
+        self.entries.get(key).is_none_or(|entry| entry.track.finished_at(time))
+    }
+
+    pub fn idle_at(&self, time: Instant) -> bool {
+        self.entries.values().all(|entry| entry.track.finished_at(time))
+    }
+
+    pub fn prune(&mut self, time: Instant) {
+        self.entries.retain(|_, entry| !entry.transient || !entry.track.finished_at(time));
+    }
+}
+
+impl<K> Default for Scheduler<K> {
+    fn default() -> Self {
+        Self { entries: HashMap::new() }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::animator::Easing;
+
+    use std::time::Duration;
+
+    /// Timeline from the origin to `(1, 0)` in `duration` seconds from `start`.
+    fn track(start: Instant, duration: f32) -> Timeline {
+        let mut timeline = Timeline::new(Point::ORIGIN).then(Point::new(1.0, 0.0), duration, Easing::Linear);
+        timeline.restart_at(start);
+        timeline
+    }
+
+    fn after(start: Instant, secs: f32) -> Instant {
+        start + Duration::from_secs_f32(secs)
+    }
+
+    #[test]
+    fn tracks_end_independently() {
+        let start = Instant::now();
+        let mut scheduler = Scheduler::default();
+        scheduler.insert("hover", track(start, 1.0));
+        scheduler.insert_transient("drop", track(start, 2.0));
+
+        let time = after(start, 1.5);
+        assert!(scheduler.finished_at(&"hover", time) && !scheduler.finished_at(&"drop", time));
+        assert!(!scheduler.idle_at(time));
+
+        // persistent tracks keep their final point, transient ones go
+        scheduler.prune(after(start, 2.5));
+        assert_eq!(scheduler.point_at(&"hover", after(start, 2.5)), Some(Point::new(1.0, 0.0)));
+        assert!(!scheduler.contains(&"drop") && scheduler.finished_at(&"drop", start));
+    }
+
+    #[test]
+    fn inserting_a_name_again_replaces_its_track() {
+        let start = Instant::now();
+        let mut scheduler = Scheduler::default();
+        scheduler.insert("coin", track(start, 1.0));
+        scheduler.insert("coin", track(start, 4.0));
+
+        assert_eq!(scheduler.point_at(&"coin", after(start, 2.0)), Some(Point::new(0.5, 0.0)));
+        assert!(scheduler.timeline_mut(&"coin").is_some() && scheduler.animation_mut(&"coin").is_none());
+
+        scheduler.remove(&"coin");
+        assert_eq!(scheduler.point_at(&"coin", start), None);
+    }
+
+    #[test]
+    fn idle_once_every_track_has_ended() {
+        let start = Instant::now();
+        let mut scheduler = Scheduler::default();
+        assert!(scheduler.idle_at(start));
+
+        scheduler.insert("line", track(start, 1.0));
+        scheduler.insert_transient("pulse", track(after(start, 1.0), 1.0));
+        scheduler.insert_transient("popup", track(start, 0.5));
+
+        assert!(!scheduler.idle_at(after(start, 0.75)));
+        assert!(!scheduler.idle_at(after(start, 1.5)));
+        assert!(scheduler.idle_at(after(start, 2.5)));
+    }
+}
@@ -0,0 +1,163 @@
+use super::Easing;
+
+use iced::Point;
+use std::time::Instant;
+
+struct Keyframe {
+    target: Point,
+    duration: f32,
+    easing: Easing,
+}
+
+/// Chain of keyframes played one after the other from a start point.
+/// When repeated, each cycle starts over from the start point.
+pub struct Timeline {
+    start: Point,
+    keyframes: Vec<Keyframe>,
+    cycles: u16,
+
+    start_animation: Instant,
+}
+
+impl Timeline {
+    pub fn new(start: Point) -> Self {
+        Self {
+            start,
+            keyframes: Vec::new(),
+            cycles: 1,
+
+            start_animation: Instant::now(),
+        }
+    }
+
+    pub fn then(mut self, target: Point, duration: f32, easing: Easing) -> Self {
+        self.keyframes.push(Keyframe { target, duration, easing });
+        self
+    }
+
+    pub fn repeat(mut self, cycles: u16) -> Self {
+        self.cycles = cycles.max(1);
+        self
+    }
+
+    pub fn restart(&mut self) {
+        self.start_animation = Instant::now();
+    }
+
+    /// Start the timeline at `time`, which may be in the future to delay it.
+    pub fn restart_at(&mut self, time: Instant) {
+        self.start_animation = time;
+    }
+
+    pub fn cycle_duration(&self) -> f32 {
+        self.keyframes.iter().map(|keyframe| keyframe.duration).sum()
+    }
+
+    pub fn finished_at(&self, time: Instant) -> bool {
+        let elapsed = time.saturating_duration_since(self.start_animation).as_secs_f32();
+
+        elapsed > self.cycle_duration() * self.cycles as f32
+    }
+
+    pub fn point_at(&self, time: Instant) -> Point {
+        let Some(last) = self.keyframes.last() else {
+            return self.start;
+        };
+
+        let cycle = self.cycle_duration();
+        let mut elapsed = time.saturating_duration_since(self.start_animation).as_secs_f32();
+
+        if elapsed >= cycle * self.cycles as f32 { return last.target; }
+        elapsed %= cycle;
+
+        let mut from = self.start;
+        for keyframe in &self.keyframes {
+            if elapsed < keyframe.duration {
+                let progress = keyframe.easing.apply(elapsed / keyframe.duration);
+                return from + (keyframe.target - from) * progress;
+            }
+
+            elapsed -= keyframe.duration;
+            from = keyframe.target;
+        }
+
+        last.target
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    fn after(start: Instant, secs: f32) -> Instant {
+        start + Duration::from_secs_f32(secs)
+    }
+
+    fn close(a: Point, b: Point) -> bool {
+        (a.x - b.x).abs() < 1e-4 && (a.y - b.y).abs() < 1e-4
+    }
+
+    /// Right by 2 in 1s, then up by 1 in 2s.
+    fn chained(start: Instant, second: Easing) -> Timeline {
+        let mut timeline = Timeline::new(Point::ORIGIN)
+            .then(Point::new(2.0, 0.0), 1.0, Easing::Linear)
+            .then(Point::new(2.0, -1.0), 2.0, second);
+
+        timeline.restart_at(start);
+        timeline
+    }
+
+    #[test]
+    fn keyframes_follow_each_other() {
+        let start = Instant::now();
+        let timeline = chained(start, Easing::Linear);
+
+        assert_eq!(timeline.cycle_duration(), 3.0);
+        assert_eq!(timeline.point_at(start), Point::ORIGIN);
+        assert!(close(timeline.point_at(after(start, 0.5)), Point::new(1.0, 0.0)));
+
+        // each keyframe starts from the target of the previous one
+        assert!(close(timeline.point_at(after(start, 1.0)), Point::new(2.0, 0.0)));
+        assert!(close(timeline.point_at(after(start, 2.0)), Point::new(2.0, -0.5)));
+    }
+
+    #[test]
+    fn the_last_target_is_kept_after_the_end() {
+        let start = Instant::now();
+        let timeline = chained(start, Easing::Linear);
+
+        assert!(!timeline.finished_at(after(start, 2.9)));
+        assert!(timeline.finished_at(after(start, 3.5)));
+        assert_eq!(timeline.point_at(after(start, 10.0)), Point::new(2.0, -1.0));
+
+        // a delayed timeline waits at its start point
+        let timeline = chained(after(start, 5.0), Easing::Linear);
+        assert_eq!(timeline.point_at(start), Point::ORIGIN);
+        assert!(Timeline::new(Point::new(3.0, 3.0)).point_at(start) == Point::new(3.0, 3.0));
+    }
+
+    #[test]
+    fn each_keyframe_has_its_own_easing() {
+        let start = Instant::now();
+        let timeline = chained(start, Easing::EaseIn);
+
+        // the linear segment is untouched, the eased one lags behind
+        assert!(close(timeline.point_at(after(start, 0.5)), Point::new(1.0, 0.0)));
+
+        let eased = -Easing::EaseIn.apply(0.5);
+        assert!(close(timeline.point_at(after(start, 2.0)), Point::new(2.0, eased)));
+        assert!(eased > -0.5);
+    }
+
+    #[test]
+    fn repeated_cycles_start_over() {
+        let start = Instant::now();
+        let mut timeline = Timeline::new(Point::ORIGIN).then(Point::new(1.0, 0.0), 1.0, Easing::Linear).repeat(3);
+        timeline.restart_at(start);
+
+        assert!(close(timeline.point_at(after(start, 1.25)), Point::new(0.25, 0.0)));
+        assert!(!timeline.finished_at(after(start, 2.5)));
+        assert!(timeline.finished_at(after(start, 3.5)));
+    }
+}
@@ -0,0 +1,78 @@
+use super::{Behaviour, Intent};
+use crate::board_game::Game;
+use crate::book::OpeningBook;
+
+use std::sync::Arc;
+use std::time::Duration;
+
+/// Play the moves of an opening book while the game is in it, then let
+/// `inner` play.
+pub struct Book {
+    book: Arc<OpeningBook>,
+    inner: Box<dyn Behaviour>,
+    intent: Option<u8>,
+}
+
+impl Book {
+    pub fn new(book: Arc<OpeningBook>, inner: Box<dyn Behaviour>) -> Self {
+        Self { book, inner, intent: None }
+    }
+}
+
+impl Behaviour for Book {
+    fn process_intent(&self) -> bool {
+        self.inner.process_intent()
+    }
+
+    fn time_left(&mut self, remaining: Duration) {
+        self.inner.time_left(remaining);
+    }
+
+    fn move_time(&mut self, budget: Duration) {
+        self.inner.move_time(budget);
+    }
+
+    fn stop(&mut self) {
+        self.inner.stop();
+    }
+
+    fn error(&mut self) -> Option<String> {
+        self.inner.error()
+    }
+
+    fn evaluate(&mut self, state: Game) -> Option<Vec<f32>> {
+        self.inner.evaluate(state)
+    }
+
+    fn opponent_played(&mut self, col: u8, state: Game) {
+        self.inner.opponent_played(col, state);
+    }
+
+    fn opponent_resigned(&mut self) {
+        self.inner.opponent_resigned();
+    }
+
+    fn new_game(&mut self) {
+        self.intent = None;
+        self.inner.new_game();
+    }
+
+    fn undoable(&self) -> bool {
+        self.inner.undoable()
+    }
+
+    fn start_process(&mut self, state: Game) {
+        self.intent = self.book.lookup(&state).map(|(code, _)| code);
+
+        if self.intent.is_none() {
+            self.inner.start_process(state);
+        }
+    }
+
+    fn intent(&mut self) -> Intent {
+        match self.intent.take() {
+            Some(code) => Intent::Some(code),
+            None => self.inner.intent(),
+        }
+    }
+}
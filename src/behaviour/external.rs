@@ -0,0 +1,265 @@
+use super::{Behaviour, Intent, RESIGN};
+use crate::board_game::Game;
+use crate::protocol::Command;
+
+use rand::seq::IteratorRandom;
+use std::io::{BufRead, BufReader, Write};
+use std::process::{Child, ChildStdin, Stdio};
+use std::sync::{mpsc, Arc, Mutex};
+use std::time::{Duration, Instant};
+
+/// Extra time given to the engine on top of its budget before it is
+/// asked to stop, then before it is considered dead.
+const GRACE: Duration = Duration::from_millis(500);
+/// How often a search waiting for the engine checks for `stop`.
+const POLL: Duration = Duration::from_millis(10);
+
+/// Engine running in a subprocess and speaking the line protocol. When it
+/// crashes, times out or plays an illegal move, a random column is played
+/// instead, the problem is reported by `error` and the engine is
+/// restarted on the next move.
+pub struct External {
+    program: String,
+    args: Vec<String>,
+    timeout: Duration,
+
+    /// Held by the running search for its whole length.
+    engine: Arc<Mutex<Option<Engine>>>,
+    time_left: Option<Duration>,
+    move_time: Option<Duration>,
+    /// Asks the running search to send `stop` to the engine.
+    stop: Option<mpsc::Sender<()>>,
+    errors: (mpsc::Sender<String>, mpsc::Receiver<String>),
+    handle: Option<std::thread::JoinHandle<u8>>,
+}
+
+impl External {
+    pub fn new(program: impl Into<String>, args: Vec<String>) -> Self {
+        Self {
+            program: program.into(), args,
+            timeout: Duration::from_secs(10),
+
+            engine: Arc::default(),
+            time_left: None, move_time: None,
+            stop: None,
+            errors: mpsc::channel(),
+            handle: None,
+        }
+    }
+
+    /// Longest time the engine may think when it has no budget.
+    pub fn timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+}
+
+impl Behaviour for External {
+    fn time_left(&mut self, remaining: Duration) {
+        self.time_left = Some(remaining);
+    }
+
+    fn move_time(&mut self, budget: Duration) {
+        self.move_time = Some(budget);
+    }
+
+    fn stop(&mut self) {
+        if let Some(stop) = &self.stop {
+            let _ = stop.send(());
+        }
+    }
+
+    fn error(&mut self) -> Option<String> {
+        self.errors.1.try_recv().ok()
+    }
+
+    fn start_process(&mut self, state: Game) {
+        let time_left = self.time_left.take().map(|remaining| {
+            let empty = state.grid().iter().filter(|cell| cell.is_none()).count();
+            remaining / (empty as u32 / 2 + 1)
+        });
+
+        let budget = self.move_time.take().or(time_left);
+        let limit = budget.map_or(self.timeout, |budget| budget + GRACE);
+
+        let engine = self.engine.clone();
+        let program = self.program.clone();
+        let args = self.args.clone();
+        let errors = self.errors.0.clone();
+
+        let (stop, stopped) = mpsc::channel();
+        self.stop = Some(stop);
+
+        let handle = std::thread::spawn(move || {
+            let Ok(mut engine) = engine.lock() else {
+                let _ = errors.send(format!("{program}: crashed, playing a random move"));
+                return fallback(state);
+            };
+
+            match think(&mut engine, &program, &args, state, budget, limit, &stopped) {
+                Ok(col) => col,
+                Err(error) => {
+                    let _ = errors.send(format!("{program}: {error}, playing a random move"));
+                    *engine = None;
+
+                    fallback(state)
+                }
+            }
+        });
+
+        self.handle = Some(handle);
+    }
+
+    fn intent(&mut self) -> Intent {
+        let Some(handle) = self.handle.take() else {
+            return Intent::None;
+        };
+
+        if handle.is_finished() {
+            let Ok(play) = handle.join() else {
+                panic!("External::intent: failed to join thread !");
+            };
+
+            Intent::Some(play)
+        } else {
+            Intent::Waiting(handle)
+        }
+    }
+}
+
+fn think(engine: &mut Option<Engine>, program: &str, args: &[String], state: Game, budget: Option<Duration>, limit: Duration, stopped: &mpsc::Receiver<()>) -> Result<u8, String> {
+    if !engine.as_mut().is_some_and(Engine::running) {
+        *engine = Some(Engine::spawn(program, args)?);
+    }
+
+    let Some(engine) = engine.as_mut() else {
+        return Err(String::from("cannot start"));
+    };
+
+    // drop replies to a search that was given up
+    while engine.lines.try_recv().is_ok() {}
+
+    engine.send(&Command::Board(state))?;
+    engine.send(&Command::Go(budget))?;
+
+    let col = match engine.bestmove(limit, Some(stopped)) {
+        Err(EngineError::Timeout | EngineError::Stopped) => {
+            engine.send(&Command::Stop)?;
+            engine.bestmove(GRACE, None)
+        }
+        result => result,
+    };
+
+    let col = col.map_err(|error| match error {
+        EngineError::Timeout | EngineError::Stopped => String::from("timed out"),
+        EngineError::Exited => String::from("exited"),
+        EngineError::Io(error) => error,
+    })?;
+
+    if col == RESIGN as usize {
+        return Ok(RESIGN);
+    }
+
+    if col >= state.cols() || state.col_full(col) {
+        return Err(format!("illegal move {}", col + 1));
+    }
+
+    Ok(col as u8)
+}
+
+fn fallback(state: Game) -> u8 {
+    state.legal_moves().choose(&mut rand::thread_rng()).unwrap_or(3)
+}
+
+enum EngineError {
+    Timeout,
+    /// `stop` was asked before the engine replied.
+    Stopped,
+    Exited,
+    Io(String),
+}
+
+struct Engine {
+    child: Child,
+    stdin: ChildStdin,
+    lines: mpsc::Receiver<String>,
+}
+
+impl Engine {
+    fn spawn(program: &str, args: &[String]) -> Result<Self, String> {
+        let mut child = std::process::Command::new(program)
+            .args(args)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::inherit())
+            .spawn()
+            .map_err(|error| format!("cannot start: {error}"))?;
+
+        let (Some(stdin), Some(stdout)) = (child.stdin.take(), child.stdout.take()) else {
+            return Err(String::from("cannot open pipes"));
+        };
+
+        let (sender, lines) = mpsc::channel();
+
+        std::thread::spawn(move || {
+            for line in BufReader::new(stdout).lines() {
+                let Ok(line) = line else { break; };
+                if sender.send(line).is_err() { break; }
+            }
+        });
+
+        Ok(Self { child, stdin, lines })
+    }
+
+    fn running(&mut self) -> bool {
+        matches!(self.child.try_wait(), Ok(None))
+    }
+
+    fn send(&mut self, command: &Command) -> Result<(), String> {
+        writeln!(self.stdin, "{command}")
+            .and_then(|_| self.stdin.flush())
+            .map_err(|error| format!("cannot write: {error}"))
+    }
+
+    /// Wait for the next `bestmove` reply and return its 0-based column,
+    /// giving up early when `stopped` receives.
+    fn bestmove(&self, timeout: Duration, stopped: Option<&mpsc::Receiver<()>>) -> Result<usize, EngineError> {
+        let deadline = Instant::now() + timeout;
+
+        loop {
+            let left = deadline.saturating_duration_since(Instant::now());
+
+            let line = match self.lines.recv_timeout(left.min(POLL)) {
+                Ok(line) => line,
+                Err(mpsc::RecvTimeoutError::Timeout) if stopped.is_some_and(|stopped| stopped.try_recv().is_ok()) => {
+                    return Err(EngineError::Stopped);
+                }
+                Err(mpsc::RecvTimeoutError::Timeout) if left.is_zero() => return Err(EngineError::Timeout),
+                Err(mpsc::RecvTimeoutError::Timeout) => continue,
+                Err(mpsc::RecvTimeoutError::Disconnected) => return Err(EngineError::Exited),
+            };
+
+            if let Some(col) = line.trim().strip_prefix("bestmove ") {
+                return match col.parse::<usize>() {
+                    _ if col == "resign" => Ok(RESIGN as usize),
+                    Ok(col @ 1..) => Ok(col - 1),
+                    _ => Err(EngineError::Io(format!("invalid reply '{line}'"))),
+                };
+            }
+        }
+    }
+}
+
+impl Drop for Engine {
+    fn drop(&mut self) {
+        let _ = self.send(&Command::Quit);
+        let deadline = Instant::now() + GRACE;
+
+        while self.running() && Instant::now() < deadline {
+            std::thread::sleep(Duration::from_millis(10));
+        }
+
+        let _ = self.child.kill();
+        let _ = self.child.wait();
+    }
+}
@@ -1,13 +1,24 @@
 mod rollout;
+mod external;
+mod remote;
 mod random;
 mod human;
+mod book;
+pub mod policy;
 
 pub use rollout::Rollout;
+pub use external::External;
+pub use remote::Remote;
 pub use random::Random;
 pub use human::Human;
+pub use book::Book;
+pub use policy::Policy;
 
 use crate::board_game::Game;
 
+/// Column played by a behaviour giving up the game.
+pub const RESIGN: u8 = u8::MAX;
+
 pub enum Intent {
     Waiting(std::thread::JoinHandle<u8>),
     Some(u8),
@@ -19,6 +30,45 @@ pub trait Behaviour {
         false
     }
 
+    /// Called before `start_process` when the game is played with clocks,
+    /// so the behaviour can budget its thinking time.
+    fn time_left(&mut self, _remaining: std::time::Duration) {
+    }
+
+    /// Think exactly `budget` on the next move instead of budgeting a clock.
+    fn move_time(&mut self, _budget: std::time::Duration) {
+    }
+
+    /// Ask a running process to play as soon as possible.
+    fn stop(&mut self) {
+    }
+
+    /// Next problem met while thinking, such as a crashed engine, to be
+    /// shown to the user.
+    fn error(&mut self) -> Option<String> {
+        None
+    }
+
+    /// Score of every column for the player to move, higher is better.
+    fn evaluate(&mut self, _state: Game) -> Option<Vec<f32>> {
+        None
+    }
+
+    /// The other seat played `col`, leading to `state`.
+    fn opponent_played(&mut self, _col: u8, _state: Game) {
+    }
+
+    fn opponent_resigned(&mut self) {
+    }
+
+    fn new_game(&mut self) {
+    }
+
+    /// Whether moves may be taken back while this behaviour is seated.
+    fn undoable(&self) -> bool {
+        true
+    }
+
     fn start_process(&mut self, state: Game);
     fn intent(&mut self) -> Intent;
 }
@@ -0,0 +1,112 @@
+//! Playout policies of `Rollout`: how each move of a playout is picked.
+//! A new policy is a new implementation of the trait added to `ALL`.
+
+use crate::board_game::{move_column, Game};
+
+use rand::{rngs::ThreadRng, seq::IteratorRandom, Rng};
+use std::fmt;
+
+pub trait Policy: Sync {
+    /// Word selecting the policy in a rollout seat.
+    fn name(&self) -> &'static str;
+
+    /// Move of the player to move, `None` when no move is left.
+    fn choose(&self, game: &Game, rng: &mut ThreadRng) -> Option<u8>;
+}
+
+impl PartialEq for dyn Policy {
+    fn eq(&self, other: &Self) -> bool {
+        self.name() == other.name()
+    }
+}
+
+impl Eq for dyn Policy {}
+
+impl fmt::Debug for dyn Policy {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+pub static RANDOM: Uniform = Uniform;
+pub static TACTICAL: Tactical = Tactical;
+
+pub static ALL: [&dyn Policy; 2] = [&RANDOM, &TACTICAL];
+
+pub fn by_name(name: &str) -> Option<&'static dyn Policy> {
+    ALL.iter().copied().find(|policy| policy.name() == name)
+}
+
+/// Any legal move, all equally likely.
+pub struct Uniform;
+
+impl Policy for Uniform {
+    fn name(&self) -> &'static str {
+        "random"
+    }
+
+    fn choose(&self, game: &Game, rng: &mut ThreadRng) -> Option<u8> {
+        game.legal_moves().choose(rng)
+    }
+}
+
+/// Win at once when possible, else block a win of the next player, else
+/// a random move, moves nearer the centre being more likely.
+pub struct Tactical;
+
+impl Tactical {
+    /// Chances of the move `code` against the other moves, from 1 on the
+    /// sides to 4 in the centre of the standard grid.
+    fn weight(game: &Game, code: u8) -> u32 {
+        let centre = game.cols() / 2;
+        (centre + 1 - move_column(code).abs_diff(centre)) as u32
+    }
+}
+
+impl Policy for Tactical {
+    fn name(&self) -> &'static str {
+        "tactical"
+    }
+
+    fn choose(&self, game: &Game, rng: &mut ThreadRng) -> Option<u8> {
+        if let Some(code) = game.winning_moves().next() {
+            return Some(code);
+        }
+
+        if let Some(code) = game.blocking_moves().choose(rng) {
+            return Some(code);
+        }
+
+        let total = game.legal_moves().map(|code| Self::weight(game, code)).sum::<u32>();
+        if total == 0 { return None; }
+
+        let mut pick = rng.gen_range(0..total);
+
+        game.legal_moves().find(|&code| match pick.checked_sub(Self::weight(game, code)) {
+            Some(rest) => { pick = rest; false }
+            None => true,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn tactical_playouts_win_and_block() {
+        let mut rng = rand::thread_rng();
+
+        // red wins in column 4 rather than blocking yellow in column 7
+        let mut game = Game::default();
+        game.play_notation("172737").unwrap();
+        assert_eq!(TACTICAL.choose(&game, &mut rng), Some(3));
+
+        let mut game = Game::default();
+        game.play_notation("11223").unwrap();
+        assert!((0..20).all(|_| TACTICAL.choose(&game, &mut rng) == Some(3)));
+
+        assert_eq!(by_name("tactical"), Some(&TACTICAL as &dyn Policy));
+        assert!(by_name("greedy").is_none());
+    }
+}
@@ -1,8 +1,8 @@
 use super::{Behaviour, Intent};
 use crate::board_game::Game;
 
-use rand::prelude::SliceRandom;
-const INVALID: u8 = Game::COL as u8;
+use rand::seq::IteratorRandom;
+const INVALID: u8 = Game::MAX_COL as u8;
 
 pub struct Random {
     generator: rand::rngs::ThreadRng,
@@ -11,15 +11,9 @@ pub struct Random {
 
 impl Behaviour for Random {
     fn start_process(&mut self, state: Game) {
-        let possibilities = (0..Game::COL)
-            .filter(|&col| !state.col_full(col))
-            .collect::<Vec<usize>>();
-
-        let col = possibilities
+        self.last_gen = state.legal_moves()
             .choose(&mut self.generator)
-            .unwrap_or(&3);
-
-        self.last_gen = *col as u8;
+            .unwrap_or(3);
     }
 
     fn intent(&mut self) -> Intent {
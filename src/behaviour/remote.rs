@@ -0,0 +1,77 @@
+use super::{Behaviour, Intent};
+use crate::board_game::Game;
+use crate::net::{coins, Inbox, Message};
+
+use std::sync::{mpsc::Sender, Arc};
+
+/// Player on the other end of a LAN connection, see `net`. Local moves are
+/// sent as they are played and the remote ones are waited for.
+pub struct Remote {
+    outgoing: Sender<Message>,
+    inbox: Arc<Inbox>,
+    game: u32,
+    handle: Option<std::thread::JoinHandle<u8>>,
+}
+
+impl Remote {
+    pub fn new(outgoing: Sender<Message>, inbox: Arc<Inbox>) -> Self {
+        Self { outgoing, inbox, game: 0, handle: None }
+    }
+
+    /// Join the series at `game` instead of the first one.
+    pub fn game(mut self, game: u32) -> Self {
+        self.game = game;
+        self
+    }
+}
+
+impl Behaviour for Remote {
+    fn opponent_played(&mut self, col: u8, state: Game) {
+        let _ = self.outgoing.send(Message::Move { game: self.game, ply: coins(state), col });
+    }
+
+    fn opponent_resigned(&mut self) {
+        let _ = self.outgoing.send(Message::Resign { game: self.game });
+    }
+
+    fn new_game(&mut self) {
+        self.game += 1;
+    }
+
+    fn undoable(&self) -> bool {
+        false
+    }
+
+    fn error(&mut self) -> Option<String> {
+        self.inbox.error()
+    }
+
+    fn start_process(&mut self, state: Game) {
+        let inbox = self.inbox.clone();
+        let (game, ply) = (self.game, coins(state) + 1);
+
+        self.handle = Some(std::thread::spawn(move || inbox.wait(game, ply)));
+    }
+
+    fn intent(&mut self) -> Intent {
+        let Some(handle) = self.handle.take() else {
+            return Intent::None;
+        };
+
+        if handle.is_finished() {
+            let Ok(play) = handle.join() else {
+                panic!("Remote::intent: failed to join thread !");
+            };
+
+            Intent::Some(play)
+        } else {
+            Intent::Waiting(handle)
+        }
+    }
+}
+
+impl Drop for Remote {
+    fn drop(&mut self) {
+        self.inbox.close();
+    }
+}
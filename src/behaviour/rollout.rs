@@ -1,84 +1,86 @@
 use crate::board_game::{Game, PlayResult};
-use super::{Behaviour, Intent};
+use super::{policy, Behaviour, Intent, Policy};
 
-use rand::{prelude::SliceRandom, seq::IteratorRandom};
+use rand::seq::IteratorRandom;
+use std::sync::{atomic::{AtomicBool, Ordering}, Arc};
+use std::time::{Duration, Instant};
 
 pub struct Rollout {
     iter: u16,
     deep: u16,
+    policy: &'static dyn Policy,
 
+    time_left: Option<Duration>,
+    move_time: Option<Duration>,
+    stop: Arc<AtomicBool>,
     handle: Option<std::thread::JoinHandle<u8>>,
 }
 
+impl Rollout {
+    pub fn new(iter: u16, deep: u16) -> Self {
+        Self {
+            iter: iter.max(1), deep,
+            policy: &policy::RANDOM,
+            time_left: None, move_time: None,
+            stop: Arc::default(), handle: None,
+        }
+    }
+
+    /// Pick the moves of the playouts with `policy` instead of at random.
+    pub fn policy(mut self, policy: &'static dyn Policy) -> Self {
+        self.policy = policy;
+        self
+    }
+}
+
 impl Behaviour for Rollout {
+    fn time_left(&mut self, remaining: Duration) {
+        self.time_left = Some(remaining);
+    }
+
+    fn move_time(&mut self, budget: Duration) {
+        self.move_time = Some(budget);
+    }
+
+    fn stop(&mut self) {
+        self.stop.store(true, Ordering::Relaxed);
+    }
+
+    fn evaluate(&mut self, state: Game) -> Option<Vec<f32>> {
+        Some(scores(state, self.iter, self.deep, self.policy, None, &AtomicBool::new(false)))
+    }
+
     fn start_process(&mut self, state: Game) {
-        let Rollout { iter, deep, .. } = *self;
-        let whoami = state.player_turn();
+        let Rollout { iter, deep, policy, .. } = *self;
 
-        const LOSE_SCORE: f32 = -5.0;
-        const WIN_SCORE: f32 = 1.0;
+        // nothing to search, `intent` has no move to give
+        if state.legal_moves().is_empty() {
+            self.handle = None;
+            return;
+        }
 
-        let handle = std::thread::spawn(move || {
-            let mut rand = rand::thread_rng();
-            let mut score = [0.0; Game::COL];
-
-            for (intent, score) in score.iter_mut().enumerate() {
-                let mut start_state = state;
-                let mut intent_score = 0.0;
-
-                match start_state.play_col(intent) {
-                    PlayResult::Pass => (),
-                    PlayResult::Error => {
-                        *score = LOSE_SCORE * 2.0;
-                        continue;
-                    },
-                    PlayResult::Win(_) => {
-                        *score = WIN_SCORE;
-                        continue;
-                    },
-                }
-
-                for _ in 0..iter {
-                    let mut game = start_state;
-
-                    for actual_deep in 1..deep {
-                        let possibilities = (0..Game::COL)
-                            .filter(|&col| !game.col_full(col))
-                            .collect::<Vec<usize>>();
-
-                        let Some(&col) = possibilities.choose(&mut rand) else {
-                            break;
-                        };
-
-                        match game.play_col(col) {
-                            PlayResult::Error => unreachable!(),
-                            PlayResult::Pass => (),
-                            PlayResult::Win(_) => {
-                                let coef =  (deep - actual_deep) as f32 / deep as f32;
-                                let score = if game.player_turn() == whoami {
-                                    // on game.play_col() player turn change. So if player
-                                    // turn is mine, this mean i just lose the game.
-                                    LOSE_SCORE
-                                } else {
-                                    WIN_SCORE
-                                };
-
-                                intent_score += score * coef;
-                                break;
-                            }
-                        }
-                    }
-                }
+        // spread the remaining time over the moves we may still have to play
+        let time_left = self.time_left.take().map(|remaining| {
+            let empty = state.grid().iter().filter(|cell| cell.is_none()).count();
+            remaining / (empty as u32 / 2 + 1)
+        });
 
-                *score = intent_score / iter as f32;
-            }
+        let deadline = self.move_time.take()
+            .or(time_left)
+            .map(|budget| (Instant::now(), budget));
+
+        self.stop = Arc::default();
+        let stop = self.stop.clone();
+
+        let handle = std::thread::spawn(move || {
+            let score = move_scores(state, iter, deep, policy, deadline, &stop);
 
-            let max = *score.iter()
+            let max = score.iter()
+                .map(|&(_, score)| score)
                 .max_by(|a, b| a.partial_cmp(b).unwrap())
                 .unwrap();
 
             score.into_iter()
-                .enumerate()
                 .filter_map(|(intent, score)| {
                     if max - score <= f32::EPSILON {
                         Some(intent)
@@ -86,8 +88,8 @@ impl Behaviour for Rollout {
                         None
                     }
                 })
-                .choose(&mut rand)
-                .unwrap() as u8
+                .choose(&mut rand::thread_rng())
+                .unwrap()
         });
 
         self.handle = Some(handle);
@@ -112,6 +114,106 @@ impl Behaviour for Rollout {
 
 impl Default for Rollout {
     fn default() -> Self {
-        Self { iter: 250, deep: 5, handle: None }
+        Self::new(250, 5)
+    }
+}
+
+const LOSE_SCORE: f32 = -5.0;
+const WIN_SCORE: f32 = 1.0;
+
+/// Score of every column, full columns scoring below any legal move.
+/// Special moves are left out, see `move_scores`.
+fn scores(state: Game, iter: u16, deep: u16, policy: &dyn Policy, deadline: Option<(Instant, Duration)>, stop: &AtomicBool) -> Vec<f32> {
+    let mut score = vec![LOSE_SCORE * 2.0; state.cols()];
+
+    for (intent, intent_score) in move_scores(state, iter, deep, policy, deadline, stop) {
+        if (intent as usize) < state.cols() {
+            score[intent as usize] = intent_score;
+        }
+    }
+
+    score
+}
+
+/// Average outcome of playouts after each legal move, their moves picked
+/// by `policy`. Playouts stop early once `deadline` is over or `stop` is
+/// raised, but every move is tried at least once.
+fn move_scores(state: Game, iter: u16, deep: u16, policy: &dyn Policy, deadline: Option<(Instant, Duration)>, stop: &AtomicBool) -> Vec<(u8, f32)> {
+    let whoami = state.player_turn();
+    let mut rand = rand::thread_rng();
+    let intents = state.legal_moves();
+    let mut score = Vec::with_capacity(intents.len());
+
+    for (index, intent) in intents.enumerate() {
+        let mut start_state = state;
+        let mut intent_score = 0.0;
+
+        match start_state.play(intent) {
+            PlayResult::Pass => (),
+            PlayResult::Error => unreachable!(),
+            PlayResult::Win(_) => {
+                score.push((intent, WIN_SCORE));
+                continue;
+            },
+            PlayResult::Lose(_) => {
+                score.push((intent, LOSE_SCORE));
+                continue;
+            },
+        }
+
+        let mut played = 0;
+
+        for _ in 0..iter {
+            if played > 0 && stop.load(Ordering::Relaxed) { break; }
+
+            if let Some((start, budget)) = deadline {
+                let move_budget = budget * (index as u32 + 1) / intents.len() as u32;
+                if played > 0 && start.elapsed() >= move_budget { break; }
+            }
+
+            played += 1;
+            let mut game = start_state;
+
+            for actual_deep in 1..deep {
+                let Some(code) = policy.choose(&game, &mut rand) else {
+                    break;
+                };
+
+                // some variants let the same player move again, remember
+                // who played
+                let player = game.player_turn();
+                let winner = match game.play(code) {
+                    PlayResult::Error => unreachable!(),
+                    PlayResult::Pass => continue,
+                    PlayResult::Win(_) => player,
+                    PlayResult::Lose(lines) => lines[0].player(),
+                };
+
+                let coef = (deep - actual_deep) as f32 / deep as f32;
+                let score = if winner == whoami { WIN_SCORE } else { LOSE_SCORE };
+
+                intent_score += score * coef;
+                break;
+            }
+        }
+
+        score.push((intent, intent_score / played as f32));
+    }
+
+    score
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::protocol::decode_board;
+
+    #[test]
+    fn a_full_grid_gives_no_move() {
+        let game = decode_board("yyrryyr/rryyrry/yyrryyr/rryyrry/yyrryyr/rryyrry", "r").unwrap();
+
+        let mut rollout = Rollout::new(20, 3);
+        rollout.start_process(game);
+        assert!(matches!(rollout.intent(), Intent::None));
     }
 }
@@ -0,0 +1,24 @@
+use connect_four::book::OpeningBook;
+use connect_four::cli::Generator;
+
+use std::time::Instant;
+
+const ABOUT: &str = "\
+Search every position of the first plies and write the best moves to an
+opening book, played with the book:<FILE> seat.";
+
+fn main() {
+    let Generator { rules, plies, depth, output, .. } = Generator::new(4, "book.bin").from_env("book", ABOUT);
+
+    let start = Instant::now();
+    let book = OpeningBook::generate(rules, plies, depth, Generator::progress);
+
+    eprintln!();
+
+    if let Err(error) = book.save(&output) {
+        eprintln!("error: {error}");
+        std::process::exit(1);
+    }
+
+    println!("{} positions written to {output} in {:.1}s", book.entries().len(), start.elapsed().as_secs_f32());
+}
@@ -0,0 +1,67 @@
+use connect_four::board_game::Rules;
+use connect_four::cli::Generator;
+use connect_four::database::{Database, Value};
+
+use std::time::Instant;
+
+const ABOUT: &str = "\
+Search every position of the first plies of a two player game and write
+their values to a position database, or count them by ply.";
+
+fn main() {
+    let Generator { rules, plies, depth, output, stats, .. } = Generator::new(6, "positions.db").accept_stats().from_env("database", ABOUT);
+
+    let result = match stats {
+        Some(path) => print_stats(&path),
+        None => generate(rules, plies, depth, &output),
+    };
+
+    if let Err(error) = result {
+        eprintln!("error: {error}");
+        std::process::exit(1);
+    }
+}
+
+fn generate(rules: &'static dyn Rules, plies: u8, depth: u8, output: &str) -> Result<(), String> {
+    let start = Instant::now();
+    let database = Database::generate(rules, plies, depth, Generator::progress);
+
+    eprintln!();
+    database.save(output)?;
+
+    println!("{} positions written to {output} in {:.1}s", database.len(), start.elapsed().as_secs_f32());
+    Ok(())
+}
+
+/// One line per ply: positions won, lost and drawn by the player who
+/// started, and those left unknown.
+fn print_stats(path: &str) -> Result<(), String> {
+    let database = Database::open(path)?;
+    let mut counts = vec![[0u64; 4]; database.plies() as usize];
+
+    for record in database.records() {
+        let record = record?;
+        // the first player moves on even plies
+        let first = record.ply % 2 == 0;
+
+        let column = match record.value() {
+            Value::Win(_) if first => 0,
+            Value::Loss(_) if !first => 0,
+            Value::Win(_) | Value::Loss(_) => 1,
+            Value::Draw => 2,
+            Value::Unknown => 3,
+        };
+
+        counts[record.ply as usize][column] += 1;
+    }
+
+    println!("{} variant, searched {} moves ahead", database.variant(), database.depth());
+    println!("ply  positions       wins     losses      draws    unknown");
+
+    for (ply, [wins, losses, draws, unknown]) in counts.into_iter().enumerate() {
+        let total = wins + losses + draws + unknown;
+        println!("{ply:>3} {total:>10} {wins:>10} {losses:>10} {draws:>10} {unknown:>10}");
+    }
+
+    Ok(())
+}
@@ -0,0 +1,57 @@
+use connect_four::board_game::Clock;
+use connect_four::cli::parse_clock;
+use connect_four::net::server::{Server, DEFAULT_ADDRESS};
+
+use std::time::Duration;
+
+const USAGE: &str = "\
+Usage: server [OPTIONS]
+
+Accept bots over TCP, pair them into games and log the results.
+
+Options:
+  --address <ADDRESS>   address to listen on [default: 127.0.0.1:5000]
+  --clock <BASE+INC>    clock of every game in seconds [default: 60+1]
+  --log <FILE>          file the results are appended to [default: results.log]
+  -h, --help            print this help";
+
+fn main() {
+    let mut address = String::from(DEFAULT_ADDRESS);
+    let mut clock = Clock::new(Duration::from_secs(60), Duration::from_secs(1));
+    let mut log = String::from("results.log");
+
+    let mut args = std::env::args().skip(1);
+
+    while let Some(arg) = args.next() {
+        let mut value = || match args.next() {
+            Some(value) => value,
+            None => exit(&format!("missing value for '{arg}'")),
+        };
+
+        match arg.as_str() {
+            "--address" => address = value(),
+            "--clock" => clock = parse_clock(&value()).unwrap_or_else(|error| exit(&error)),
+            "--log" => log = value(),
+            "-h" | "--help" => {
+                println!("{USAGE}");
+                return;
+            }
+            _ => exit(&format!("unknown argument '{arg}'")),
+        }
+    }
+
+    let result = Server::bind(&address, clock, log).and_then(|server| {
+        println!("listening on {address}");
+        server.run(|error| eprintln!("warning: {error}"))
+    });
+
+    if let Err(error) = result {
+        eprintln!("error: {error}");
+        std::process::exit(1);
+    }
+}
+
+fn exit(error: &str) -> ! {
+    eprintln!("error: {error}\n\n{USAGE}");
+    std::process::exit(2);
+}
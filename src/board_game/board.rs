@@ -1,20 +1,28 @@
-use crate::behaviour::{Behaviour, Intent};
-use crate::animator::Animation;
-use super::{Game, Player};
+use crate::behaviour::{Behaviour, Intent, RESIGN};
+use crate::animator::{Animation, Easing, Fall, Scheduler, Timeline};
+use crate::net::Broadcast;
+use crate::analysis::Analysis;
+use super::{move_column, Clock, Game, Line, PlayResult, Player, Score, BOMB, POP};
 
 use iced::{
     widget::canvas, Point, Vector, Rectangle,
     Command, Color,
 };
 
-use std::time::Instant;
+use std::time::{Duration, Instant};
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub enum Message {
     Tick(Instant),
     Slide(u8),
     Play(u8),
     Restart,
+    Undo,
+    Resign,
+    /// Select the next special move played by right clicks.
+    Special,
+    /// Show or hide the threats of each player.
+    Threats,
 }
 
 pub struct Board {
@@ -23,18 +31,38 @@ pub struct Board {
     board: canvas::Cache,
 
     sector: u8,
+    code: u8,
+    special: usize,
     now: Instant,
-    animation: Animation,
+    animations: Scheduler<Anim>,
     board_state: BoardState,
+    win_lines: Vec<Line>,
+    /// Mark the empty cells completing a line of a player.
+    threats: bool,
+    /// Text and colour of the last score pop-up.
+    popup: (String, Color),
+    /// Position of the last game, fading out after a restart.
+    fading: Game,
+    /// Last problem reported by a behaviour, until the next game.
+    error: Option<String>,
 
     game: Game,
-    p1: Box<dyn Behaviour>,
-    p2: Box<dyn Behaviour>,
+    /// Positions before each move of the game.
+    history: Vec<Game>,
+    score: Score,
+    clock: Option<Clock>,
+    broadcast: Option<Broadcast>,
+    /// The player to move of the first position was asked to play.
+    started: bool,
+    /// Behaviour of each seated player.
+    seats: Vec<(Player, Box<dyn Behaviour>)>,
 }
 
 impl Board {
     const YELLOW_PLAYER: Color = Color::from_rgb(0.8, 0.8, 0.1);
     const RED_PLAYER: Color = Color::from_rgb(0.8, 0.1, 0.1);
+    const GREEN_PLAYER: Color = Color::from_rgb(0.1, 0.55, 0.25);
+    const BLUE_PLAYER: Color = Color::from_rgb(0.55, 0.85, 1.0);
 
     const BACKGROUND: Color = Color::from_rgb(0.275, 0.47, 0.785);
     const BOARD_COLOR: Color = Color::from_rgb(0.1, 0.1, 0.5);
@@ -42,14 +70,28 @@ impl Board {
 
     const GRID_OPENING: f32 = 0.8;
     const COIN_SIZE: f32 = 0.85;
+    const THREAT_SIZE: f32 = 0.3;
 
-    pub fn new(mut p1: Box<dyn Behaviour>, p2: Box<dyn Behaviour>) -> Self {
-        let animation = crate::animator::Builder::default()
-            .move_curve(Point::new(Game::COL as f32 / 2.0, -0.5), Vector::new(0.0, 1.0))
+    const WIN_LINE_DURATION: f32 = 1.0;
+    const WIN_PULSE_PERIOD: f32 = 0.6;
+    const WIN_PULSES: u16 = 4;
+
+    const POPUP_DURATION: f32 = 1.2;
+    const FADE_DURATION: f32 = 0.4;
+
+    /// Seat 2 to 4 players in turn order, the first one starting unless
+    /// `position` says otherwise. The player to move starts thinking on
+    /// the first tick, once the board is set up.
+    pub fn new(seats: Vec<(Player, Box<dyn Behaviour>)>) -> Self {
+        let coin = crate::animator::Builder::default()
+            .move_curve(Point::new(view_cols(&Game::default()) as f32 / 2.0, -0.5), Vector::new(0.0, 1.0))
             .anim_duration(0.5).build();
 
-        let game = Game::default();
-        p1.start_process(game);
+        let mut animations = Scheduler::default();
+        animations.insert(Anim::Coin, coin);
+
+        let players = seats.iter().map(|(player, _)| *player).collect::<Vec<_>>();
+        let game = Game::new(players[0]).seating(&players);
 
         Self {
             game_state: canvas::Cache::default(),
@@ -57,14 +99,77 @@ impl Board {
             board: canvas::Cache::default(),
 
             sector: 3,
+            code: 3,
+            special: 0,
             now: Instant::now(),
-            animation,
+            animations,
             board_state: BoardState::Initialize,
-
-            game, p1, p2,
+            win_lines: Vec::new(),
+            threats: false,
+            popup: (String::new(), Color::WHITE),
+            fading: game,
+            error: None,
+
+            game, seats,
+            history: Vec::new(),
+            score: Score::default(),
+            clock: None,
+            broadcast: None,
+            started: false,
         }
     }
 
+    /// Start from `game` instead of an empty grid.
+    pub fn position(mut self, game: Game) -> Self {
+        self.game = game;
+        self.sector = game.cols() as u8 / 2;
+        self.initialize_coin();
+        self
+    }
+
+    /// Play with clocks.
+    pub fn clock(mut self, clock: Clock) -> Self {
+        self.clock = Some(clock);
+        self
+    }
+
+    /// Mark the threats of each player from the start.
+    pub fn threats(mut self, threats: bool) -> Self {
+        self.threats = threats;
+        self
+    }
+
+    pub fn best_of(mut self, games: u16) -> Self {
+        self.score = Score::best_of(games);
+        self
+    }
+
+    /// Carry on a match from `score`.
+    pub fn standings(mut self, score: Score) -> Self {
+        self.score = score;
+        self
+    }
+
+    /// Send the games to spectators, from the current position on.
+    pub fn broadcast(mut self, broadcast: Broadcast) -> Self {
+        broadcast.new_game(self.game, self.score, self.clock);
+        self.broadcast = Some(broadcast);
+        self
+    }
+
+    /// Show `error` in the status until the next game, as the errors of
+    /// behaviours.
+    pub fn report(&mut self, error: String) {
+        self.error = Some(error);
+    }
+
+    /// Show the remaining times of a clock running elsewhere.
+    pub fn sync_clock(&mut self, red: Duration, yellow: Duration) {
+        self.clock
+            .get_or_insert_with(|| Clock::new(Duration::ZERO, Duration::ZERO))
+            .sync(red, yellow);
+    }
+
     pub fn handle_message(&mut self, message: Message) -> Command<Message> {
         match message {
             Message::Tick(now) => {
@@ -79,20 +184,98 @@ impl Board {
             Message::Restart => {
                 self.restart();
             },
+            Message::Undo => {
+                self.undo();
+            },
+            Message::Resign => {
+                if self.behaviour().process_intent() {
+                    self.resign();
+                }
+            },
+            Message::Special => {
+                let count = self.game.rules().special_moves().len().max(1);
+                self.special = (self.special + 1) % count;
+            },
+            Message::Threats => {
+                self.threats = !self.threats;
+                self.game_state.clear();
+            },
         }
 
         Command::none()
     }
 
+    /// Nothing moves on the board, the first tick is still awaited until
+    /// the behaviours are started.
     pub fn animation_finished(&self) -> bool {
-        self.animation.finished_at(self.now)
+        self.started && self.animations.idle_at(self.now)
+    }
+
+    pub fn score(&self) -> Score {
+        self.score
+    }
+
+    /// Seated players in turn order.
+    pub fn players(&self) -> &[Player] {
+        self.game.players()
+    }
+
+    pub fn clock_state(&self) -> Option<Clock> {
+        self.clock
+    }
+
+    pub fn clock_running(&self) -> bool {
+        self.clock.is_some() && !self.board_state.finished()
+    }
+
+    pub fn match_over(&self) -> bool {
+        self.board_state.finished() && self.score.match_over()
+    }
+
+    /// Last problem of a behaviour, state of the variant and the special
+    /// move played by right clicks.
+    pub fn status(&self) -> Option<String> {
+        let status = match (&self.error, self.game.rules().status(&self.game)) {
+            (Some(error), Some(status)) => Some(format!("{error}    {status}")),
+            (error, status) => error.clone().or(status),
+        };
+        let special = self.special_move().map(|(_, name)| match self.game.rules().special_moves().len() {
+            1 => format!("right click: {name}"),
+            _ => format!("right click: {name}, tab to change"),
+        });
+
+        match (status, special) {
+            (Some(status), Some(special)) => Some(format!("{status}    {special}")),
+            (status, special) => status.or(special),
+        }
     }
 
     fn update(&mut self, now: Instant) -> Command<Message> {
         self.animator.clear();
+        self.animations.prune(now);
         self.now = now;
 
-        if self.animation.finished_at(now) {
+        if !self.started {
+            self.start_behaviour();
+        }
+
+        for (_, behaviour) in &mut self.seats {
+            if let Some(error) = behaviour.error() {
+                self.error = Some(error);
+            }
+        }
+
+        if let Some(player) = self.tick_clock(now) {
+            self.board_state.new_action(BoardState::Forfeit);
+            self.game_state.clear();
+            self.record_forfeit(player);
+
+            return Command::none();
+        }
+
+        let idle = [Anim::Coin, Anim::Drop, Anim::Pop, Anim::Blast].iter().all(|anim| self.animations.finished_at(anim, now));
+
+        if idle {
             match self.board_state {
                 BoardState::Initialize => {
                     self.board_state.new_action(BoardState::Waiting);
@@ -105,40 +288,76 @@ impl Board {
                         }
                     }
                 },
-                BoardState::InitThenPlay => self.play(self.sector),
+                BoardState::InitThenPlay => self.play(self.code),
                 BoardState::SlideThenPlay => {
-                    let height = self.game.col_height(self.sector as usize);
+                    let landing = self.game.rules().landing(&self.game, self.code)
+                        .filter(|_| self.game.legal_moves().contains(self.code));
 
-                    if height != Game::ROW {
+                    if let Some(row) = landing {
                         self.board_state.new_action(BoardState::Playing);
-                        self.play_current_sector(height);
+                        self.play_current_sector(row);
                     } else {
                         self.board_state.new_action(BoardState::Waiting);
                     }
                 },
                 BoardState::Playing => {
                     self.game_state.clear();
+                    let player = self.game.player_turn();
+
+                    if let Some(clock) = &mut self.clock {
+                        clock.press(player);
+                    }
+
+                    let code = self.code;
+                    self.history.push(self.game);
 
-                    let action = if let super::PlayResult::Win([x1, y1, x2, y2]) = self.game.play_col(self.sector as usize) {
+                    let result = self.game.play(code);
+                    let game = self.game;
+                    for (seat, behaviour) in &mut self.seats {
+                        if *seat != player {
+                            behaviour.opponent_played(code, game);
+                        }
+                    }
+
+                    if let Some(broadcast) = &self.broadcast {
+                        broadcast.played(code, game, self.clock);
+                    }
+
+                    let winner = match result {
+                        PlayResult::Win(lines) => Some((player, lines)),
+                        PlayResult::Lose(lines) => Some((lines[0].player(), lines)),
+                        PlayResult::Pass | PlayResult::Error => None,
+                    };
+
+                    let action = if let Some((winner, lines)) = winner {
+                        self.record_result(Some(winner));
                         self.sliding_curve();
 
-                        let start = Point { x: 0.5 + x1 as f32, y: 0.5 + Game::ROW as f32 - y1 as f32 };
-                        let end = Point { x: 0.5 + x2 as f32, y: 0.5 + Game::ROW as f32 - y2 as f32 };
-                        let direction = end - start;
+                        // the animation only drives the progress of every line
+                        let mut win_line = crate::animator::Builder::default()
+                            .move_curve(Point::ORIGIN, Vector::new(1.0, 0.0))
+                            .anim_duration(Self::WIN_LINE_DURATION)
+                            .build();
+                        win_line.restart();
 
-                        self.animation.update_axis(start, direction);
-                        self.animation.update_duration(1.0);
-                        self.animation.restart();
+                        let mut win_pulse = Timeline::new(Point::ORIGIN)
+                            .then(Point::new(0.4, 0.0), Self::WIN_PULSE_PERIOD / 2.0, Easing::EaseInOut)
+                            .then(Point::ORIGIN, Self::WIN_PULSE_PERIOD / 2.0, Easing::EaseInOut)
+                            .repeat(Self::WIN_PULSES);
+                        win_pulse.restart_at(now + Duration::from_secs_f32(Self::WIN_LINE_DURATION));
+
+                        self.animations.insert(Anim::WinLine, win_line);
+                        self.animations.insert_transient(Anim::WinPulse, win_pulse);
+                        self.win_lines = lines;
 
                         BoardState::Win
-                    } else if self.game.grid_full() {
+                    } else if self.game.stalemate() {
+                        self.record_result(None);
                         BoardState::Finished
                     } else {
-                        self.sector = Game::COL as u8 / 2;
+                        self.sector = self.game.cols() as u8 / 2;
                         self.initialize_coin();
-
-                        let state = self.game;
-                        self.behaviour_mut().start_process(state);
+                        self.start_behaviour();
 
                         BoardState::Initialize
                     };
@@ -157,7 +376,7 @@ impl Board {
     fn slide(&mut self, sector: u8) {
         match self.board_state {
             BoardState::Sliding => {
-                self.animation.update_ctrl(
+                self.coin().update_ctrl(
                     Point { x: 0.4, y: 0.4 },
                     Point { x: 0.5, y: 1.0 }
                 );
@@ -172,59 +391,254 @@ impl Board {
         self.slide_sector(sector);
     }
 
-    fn play(&mut self, sector: u8) {
+    fn play(&mut self, code: u8) {
+        if self.board_state.finished() {
+            return;
+        }
+
+        if code == RESIGN {
+            self.resign();
+            return;
+        }
+
+        let sector = move_column(code) as u8;
+        if sector as usize >= self.game.cols() {
+            return;
+        }
+
         if self.board_state == BoardState::Initialize {
             self.board_state.new_action(BoardState::InitThenPlay);
-            self.sector = sector;
+            self.code = code;
             return;
         }
 
-        let height = self.game.col_height(sector as usize);
-        if height == Game::ROW {
+        if !self.game.legal_moves().contains(code) {
             self.board_state.new_action(BoardState::Sliding);
             self.slide_sector(sector);
             return;
         }
 
+        let Some(row) = self.game.rules().landing(&self.game, code) else {
+            match code & BOMB {
+                0 => self.pop(code),
+                _ => self.blast(code),
+            }
+            return;
+        };
+
         if self.board_state == BoardState::InitThenPlay {
             self.sector = 3; // avoid code duplication
         }
 
+        self.code = code;
+
         if self.sector == sector && self.board_state.new_action(BoardState::Playing) {
-            self.play_current_sector(height);
+            self.play_current_sector(row);
         } else if self.board_state.new_action(BoardState::SlideThenPlay) {
             self.slide_sector(sector);
         }
     }
 
+    /// Take the bottom coin of a column out, the column falls by one cell.
+    fn pop(&mut self, code: u8) {
+        if !self.board_state.new_action(BoardState::Playing) {
+            return;
+        }
+
+        self.sector = move_column(code) as u8;
+        self.code = code;
+        self.game_state.clear();
+
+        let mut fall = Fall::new(Point::ORIGIN, 1.0);
+        fall.restart();
+
+        self.animations.insert_transient(Anim::Pop, fall);
+    }
+
+    /// Blow up the top coin of a column, it fades out.
+    fn blast(&mut self, code: u8) {
+        if !self.board_state.new_action(BoardState::Playing) {
+            return;
+        }
+
+        self.sector = move_column(code) as u8;
+        self.code = code;
+        self.game_state.clear();
+
+        // x is the opacity of the coin
+        let mut fade = Timeline::new(Point::new(1.0, 0.0))
+            .then(Point::ORIGIN, Self::FADE_DURATION, Easing::EaseIn);
+        fade.restart();
+
+        self.animations.insert_transient(Anim::Blast, fade);
+    }
+
     fn restart(&mut self) {
         if self.board_state.finished() {
+            if self.score.match_over() {
+                self.score.reset();
+            }
+
+            if let Some(clock) = &mut self.clock {
+                clock.reset();
+            }
+
+            // the coins of the last game fade out over the new one
+            let mut fade = Timeline::new(Point::new(1.0, 0.0))
+                .then(Point::ORIGIN, Self::FADE_DURATION, Easing::EaseIn);
+            fade.restart();
+
+            self.fading = self.game;
+            self.animations.insert_transient(Anim::FadeOut, fade);
+
             self.game.restart();
+            self.history.clear();
+            self.win_lines.clear();
+            self.error = None;
 
-            let game = self.game;
-            self.behaviour_mut().start_process(game);
+            for (_, behaviour) in &mut self.seats {
+                behaviour.new_game();
+            }
+            self.start_behaviour();
+
+            if let Some(broadcast) = &self.broadcast {
+                broadcast.new_game(self.game, self.score, self.clock);
+            }
 
             self.board_state.new_action(BoardState::Initialize);
-            self.animation.update_duration(0.5);
-            self.animation.restart();
+            self.animations.remove(&Anim::WinLine);
+            self.animations.remove(&Anim::WinPulse);
+
+            let coin = self.coin();
+            coin.update_duration(0.5);
+            coin.restart();
             self.game_state.clear();
 
-            self.sector = Game::COL as u8 / 2;
+            self.sector = self.game.cols() as u8 / 2;
             self.initialize_coin();
         }
     }
 
+    fn start_behaviour(&mut self) {
+        self.started = true;
+        let game = self.game;
+        let remaining = self.clock.map(|clock| clock.remaining(game.player_turn()));
+        let behaviour = self.behaviour_mut();
+
+        if let Some(remaining) = remaining {
+            behaviour.time_left(remaining);
+        }
+
+        behaviour.start_process(game);
+    }
+
+    fn tick_clock(&mut self, now: Instant) -> Option<Player> {
+        let active = match self.board_state {
+            BoardState::Playing | BoardState::Win | BoardState::Finished | BoardState::Forfeit => None,
+            _ => Some(self.game.player_turn()),
+        };
+
+        self.clock.as_mut()?.tick(now, active)
+    }
+
+    /// The player to move gives up the game.
+    fn resign(&mut self) {
+        let player = self.game.player_turn();
+
+        if self.board_state.new_action(BoardState::Forfeit) {
+            self.game_state.clear();
+            self.record_forfeit(player);
+
+            for (seat, behaviour) in &mut self.seats {
+                if *seat != player {
+                    behaviour.opponent_resigned();
+                }
+            }
+        }
+    }
+
+    /// `player` lost on time or resigned: every other player wins.
+    fn record_forfeit(&mut self, player: Player) {
+        match self.game.opponent_of(player) {
+            Some(winner) => self.record_result(Some(winner)),
+            None => {
+                self.score.record_forfeit(player, self.game.players());
+                self.show_popup(format!("+1 all but {}", player.name()), Color::WHITE);
+            }
+        }
+    }
+
+    /// Count the game in the score, tell the spectators and pop the point
+    /// up over the grid.
+    fn record_result(&mut self, winner: Option<Player>) {
+        match winner {
+            Some(winner) => self.score.record_win(winner),
+            None => self.score.record_draw(),
+        }
+
+        if let Some(broadcast) = &self.broadcast {
+            broadcast.finished(winner);
+        }
+
+        match winner {
+            Some(winner) => self.show_popup(format!("+1 {}", winner.name()), Self::color(winner)),
+            None => self.show_popup(String::from("draw"), Color::WHITE),
+        }
+    }
+
+    fn show_popup(&mut self, content: String, color: Color) {
+        // x is the opacity and y the rise of the text
+        let mut popup = Timeline::new(Point::new(1.0, 0.0))
+            .then(Point::new(1.0, -0.3), Self::POPUP_DURATION / 3.0, Easing::EaseOut)
+            .then(Point::new(0.0, -0.6), Self::POPUP_DURATION * 2.0 / 3.0, Easing::EaseIn);
+        popup.restart();
+
+        self.popup = (content, color);
+        self.animations.insert_transient(Anim::ScorePopup, popup);
+    }
+
+    /// Take back moves until a human player is to play again.
+    fn undo(&mut self) {
+        if self.board_state != BoardState::Waiting || !self.seats.iter().all(|(_, behaviour)| behaviour.undoable()) {
+            return;
+        }
+
+        let mut history = self.history.clone();
+
+        let game = loop {
+            let Some(game) = history.pop() else {
+                return;
+            };
+
+            if self.behaviour_of(game.player_turn()).process_intent() {
+                break game;
+            }
+        };
+
+        if let Some(broadcast) = &self.broadcast {
+            broadcast.took_back(self.history.len() - history.len());
+        }
+
+        self.game = game;
+        self.history = history;
+        self.game_state.clear();
+        self.start_behaviour();
+    }
+
     fn initialize_coin(&mut self) {
         self.sliding_curve();
-        self.animation.restart();
-        self.animation.update_axis(
-            Point::new(Game::COL as f32 / 2.0, -0.5),
+
+        let centre = view_cols(&self.game) as f32 / 2.0;
+        let coin = self.coin();
+        coin.restart();
+        coin.update_axis(
+            Point::new(centre, -0.5),
             Vector::new(0.0, 1.0),
         );
     }
 
     fn sliding_curve(&mut self) {
-        self.animation.update_ctrl(
+        self.coin().update_ctrl(
             Point { x: 0.5, y: 0.0 },
             Point { x: 0.5, y: 1.0 }
         );
@@ -233,131 +647,294 @@ impl Board {
     fn slide_sector(&mut self, sector: u8) {
         self.sector = sector;
 
-        let start = self.animation.point_at(self.now);
-        let coin_x = sector as f32 + 0.5;
+        let start = self.coin_at(self.now);
+        let coin_x = self.sector_x(sector);
         let coin_y = 0.5;
 
         let direction = Point{ x: coin_x, y: coin_y } - start;
-        self.animation.update_axis(start, direction);
-        self.animation.restart();
+        let coin = self.coin();
+        coin.update_axis(start, direction);
+        coin.restart();
     }
 
     fn play_current_sector(&mut self, height: usize) {
-        self.animation.update_ctrl(
-            Point { x: 0.65, y: 0.0 },
-            Point { x: 0.75, y: 0.5 }
-        );
+        let start = Point { x: self.sector_x(self.sector), y: 0.5 };
+        let mut fall = Fall::new(start, (self.game.rows() - height) as f32);
+        fall.restart();
 
-        self.animation.update_axis(
-            Point { x: self.sector as f32 + 0.5, y: 0.5 },
-            Vector { x: 0.0, y: (Game::ROW - height) as f32 }
-        );
+        self.animations.insert_transient(Anim::Drop, fall);
+    }
+
+    fn coin(&mut self) -> &mut Animation {
+        self.animations.animation_mut(&Anim::Coin).expect("Board::coin: the coin is always scheduled")
+    }
+
+    fn coin_at(&self, time: Instant) -> Point {
+        self.animations.point_at(&Anim::Drop, time)
+            .or_else(|| self.animations.point_at(&Anim::Coin, time))
+            .unwrap_or(Point::ORIGIN)
+    }
+
+    /// Horizontal position of the centre of `sector`, in cells.
+    fn sector_x(&self, sector: u8) -> f32 {
+        (sector as usize + self.game.rules().side_columns()) as f32 + 0.5
+    }
 
-        self.animation.restart();
+    /// The move being animated takes a coin out of a column.
+    fn popping(&self) -> bool {
+        self.board_state == BoardState::Playing && self.code & POP != 0
+    }
+
+    /// The move being animated blows up the top coin of a column.
+    fn blasting(&self) -> bool {
+        self.board_state == BoardState::Playing && self.code & BOMB != 0
+    }
+
+    fn special_move(&self) -> Option<(u8, &'static str)> {
+        self.game.rules().special_moves().get(self.special).copied()
+    }
+
+    /// Sector under the horizontal position `x` of the canvas.
+    fn sector_at(&self, x: f32, bounds: Rectangle) -> u8 {
+        let (offset, chunk_size) = offset_and_chunk_size(bounds.size(), &self.game);
+        let side = self.game.rules().side_columns() as f32;
+
+        ((x - offset.x) / chunk_size - side).clamp(0.0, self.game.cols() as f32 - 1.0) as u8
+    }
+
+    fn winning_cell(&self, col: i8, row: i8) -> bool {
+        self.win_lines.iter().any(|line| line.cells().contains(&(col, row)))
     }
 
     fn behaviour(&self) -> &dyn Behaviour {
-        match self.game.player_turn() {
-            Player::Yellow => self.p2.as_ref(),
-            Player::Red => self.p1.as_ref(),
-        }
+        self.behaviour_of(self.game.player_turn())
+    }
+
+    fn behaviour_of(&self, player: Player) -> &dyn Behaviour {
+        self.seats.iter()
+            .find(|(seat, _)| *seat == player)
+            .map(|(_, behaviour)| behaviour.as_ref())
+            .expect("every player of the game is seated")
     }
 
     fn behaviour_mut(&mut self) -> &mut Box<dyn Behaviour> {
-        match self.game.player_turn() {
-            Player::Yellow => &mut self.p2,
-            Player::Red => &mut self.p1,
+        self.behaviour_of_mut(self.game.player_turn())
+    }
+
+    fn behaviour_of_mut(&mut self, player: Player) -> &mut Box<dyn Behaviour> {
+        self.seats.iter_mut()
+            .find(|(seat, _)| *seat == player)
+            .map(|(_, behaviour)| behaviour)
+            .expect("every player of the game is seated")
+    }
+
+    fn color(player: Player) -> Color {
+        match player {
+            Player::Red => Self::RED_PLAYER,
+            Player::Yellow => Self::YELLOW_PLAYER,
+            Player::Green => Self::GREEN_PLAYER,
+            Player::Blue => Self::BLUE_PLAYER,
         }
     }
 }
 
-fn offset_and_chunk_size(bounds: iced::Size) -> (Point, f32) {
+/// Columns drawn, side columns included.
+fn view_cols(game: &Game) -> usize {
+    game.cols() + 2 * game.rules().side_columns()
+}
+
+/// The grid of `game` and the row above it fit in `bounds`, centred.
+fn offset_and_chunk_size(bounds: iced::Size, game: &Game) -> (Point, f32) {
     let iced::Size{ width, height } = bounds;
-    if width >= height {
-        (Point{ x: (width - height) / 2.0, y: 0.0 }, height / Game::COL as f32)
-    } else {
-        (Point{ x: 0.0, y: (height - width) / 2.0 }, width / Game::COL as f32)
-    }
+    let (cols, rows) = (view_cols(game), game.rows());
+    let chunk_size = (width / cols as f32).min(height / (rows + 1) as f32);
+
+    let x = (width - chunk_size * cols as f32) / 2.0;
+    let y = (height - chunk_size * (rows + 1) as f32) / 2.0;
+
+    (Point { x, y }, chunk_size)
 }
 
 impl canvas::Program<Message> for Board {
     type State = ();
 
     fn draw(&self, _state: &Self::State, _theme: &iced::Theme, bounds: Rectangle, _cursor: canvas::Cursor) -> Vec<canvas::Geometry> {
-        let (offset, chunk_size) = offset_and_chunk_size(bounds.size());
+        let (cols, rows) = (view_cols(&self.game), self.game.rows());
+        let side = self.game.rules().side_columns() as i8;
+        let (offset, chunk_size) = offset_and_chunk_size(bounds.size(), &self.game);
 
         let game_state = self.game_state.draw(bounds.size(), |frame| {
             let background = canvas::Path::rectangle(Point::ORIGIN, frame.size());
             frame.fill(&background, Self::BACKGROUND);
 
-            let (mut x, mut y) = (0.0, Game::ROW as f32);
+            let cells = (-side..self.game.cols() as i8 + side).flat_map(|col| (0..rows).map(move |row| (col, row)));
+
+            for (col, row) in cells {
+                let cell = self.game.cell(col, row);
+                let (x, y) = ((col + side) as f32, (rows - row) as f32);
+
+                // the popping column and the blasted coin are drawn by the animator
+                if self.popping() && col == self.sector as i8 {
+                    continue;
+                }
+
+                if self.blasting() && col == self.sector as i8 && row + 1 == self.game.col_height(col as usize) {
+                    continue;
+                }
 
-            for cell in &self.game.grid() {
                 let coin = canvas::Path::rectangle(
-                    Point { x: x * chunk_size, y: y * chunk_size },
+                    offset + Vector { x: x * chunk_size, y: y * chunk_size },
                     iced::Size { width: chunk_size, height: chunk_size }
                 );
 
-                y -= 1.0;
+                let Some(color) = cell.map(Self::color) else {
+                    continue;
+                };
 
-                if y < 0.5 {
-                    y = Game::ROW as f32;
-                    x += 1.0;
+                if self.board_state == BoardState::Win && !self.winning_cell(col, row as i8) {
+                    frame.fill(&coin, Color { a: 0.35, ..color });
+                } else {
+                    frame.fill(&coin, color);
                 }
+            }
 
-                let color = match cell {
-                    Some(Player::Yellow) => Self::YELLOW_PLAYER,
-                    Some(Player::Red) => Self::RED_PLAYER,
-                    None => continue,
-                };
+            if !self.threats || self.board_state.finished() {
+                return;
+            }
+
+            // threats the other players would win first are faded, a
+            // cell threatened by several players gets nested marks
+            let analysis = Analysis::new(&self.game);
+            let threats = analysis.threats();
+
+            for (index, threat) in threats.iter().enumerate() {
+                let nested = threats[..index].iter().filter(|other| (other.col, other.row) == (threat.col, threat.row)).count();
+                let rad = chunk_size * Self::THREAT_SIZE * 0.5 * 0.6f32.powi(nested as i32);
+
+                let coef = Vector { x: 0.5 + threat.col as f32 + side as f32, y: 0.5 + (rows - threat.row) as f32 };
+                let mark = canvas::Path::circle(offset + coef * chunk_size, rad);
 
-                frame.fill(&coin, color);
+                let color = Self::color(threat.player);
+                frame.fill(&mark, if analysis.useful(threat) { color } else { Color { a: 0.35, ..color } });
             }
         });
 
         let animator = self.animator.draw(bounds.size(), |frame| {
+            if let Some(Point { x: opacity, .. }) = self.animations.point_at(&Anim::FadeOut, self.now) {
+                for (col, row) in (0..self.fading.cols()).flat_map(|col| (0..self.fading.col_height(col)).map(move |row| (col, row))) {
+                    let Some(color) = self.fading.cell(col as i8, row).map(Self::color) else {
+                        continue;
+                    };
+
+                    let (x, y) = ((col + side as usize) as f32, (rows - row) as f32);
+                    let coin = canvas::Path::rectangle(
+                        offset + Vector { x: x * chunk_size, y: y * chunk_size },
+                        iced::Size { width: chunk_size, height: chunk_size }
+                    );
+
+                    frame.fill(&coin, Color { a: opacity, ..color });
+                }
+            }
+
+            if let Some(Point { x: opacity, y: rise }) = self.animations.point_at(&Anim::ScorePopup, self.now) {
+                let (content, color) = self.popup.clone();
+
+                frame.fill_text(canvas::Text {
+                    content,
+                    position: offset + Vector { x: cols as f32 / 2.0, y: 0.5 + rise } * chunk_size,
+                    color: Color { a: opacity, ..color },
+                    size: chunk_size * 0.6,
+                    horizontal_alignment: iced::alignment::Horizontal::Center,
+                    vertical_alignment: iced::alignment::Vertical::Center,
+                    ..canvas::Text::default()
+                });
+            }
+
             match self.board_state {
-                BoardState::Finished => (),
+                BoardState::Finished | BoardState::Forfeit => (),
                 BoardState::Win => {
                     let rad = chunk_size * Self::COIN_SIZE * 0.1;
+                    let progress = self.animations.point_at(&Anim::WinLine, self.now).map_or(1.0, |point| point.x);
+
+                    let cell_pos = |(col, row): (i8, i8)| {
+                        let coef = Vector { x: 0.5 + (col + side) as f32, y: 0.5 + rows as f32 - row as f32 };
+                        offset + coef * chunk_size
+                    };
 
-                    let start_coef = self.animation.start_point() - Point::ORIGIN;
-                    let start_vec = start_coef * chunk_size;
-                    let start_pos = offset + start_vec;
+                    let glow = self.animations.point_at(&Anim::WinPulse, self.now).map_or(0.0, |point| point.x);
+                    if glow > 0.0 {
+                        let glow = Color { a: glow, ..Color::WHITE };
+                        let glow_rad = chunk_size * 0.5 * Self::GRID_OPENING;
+
+                        for &cell in self.win_lines.iter().flat_map(Line::cells) {
+                            frame.fill(&canvas::Path::circle(cell_pos(cell), glow_rad), glow);
+                        }
+                    }
 
-                    let end_coef = self.animation.point_at(self.now) - Point::ORIGIN;
-                    let end_vec = end_coef * chunk_size;
-                    let end_pos = offset + end_vec;
+                    for line in &self.win_lines {
+                        let start_pos = cell_pos(line.start());
+                        let end_pos = start_pos + (cell_pos(line.end()) - start_pos) * progress;
 
-                    let start = canvas::Path::circle(start_pos, rad);
-                    let end = canvas::Path::circle(end_pos, rad);
+                        let start = canvas::Path::circle(start_pos, rad);
+                        let end = canvas::Path::circle(end_pos, rad);
 
-                    frame.fill(&start, Self::WIN_COLOR);
-                    frame.fill(&end, Self::WIN_COLOR);
+                        frame.fill(&start, Self::WIN_COLOR);
+                        frame.fill(&end, Self::WIN_COLOR);
 
-                    let Vector { x, y } = end_pos - start_pos;
-                    let len = (x*x + y*y).sqrt();
+                        let Vector { x, y } = end_pos - start_pos;
+                        let len = (x*x + y*y).sqrt();
 
-                    frame.translate(start_pos - Point::ORIGIN);
-                    frame.rotate(y.atan2(x));
+                        frame.with_save(|frame| {
+                            frame.translate(start_pos - Point::ORIGIN);
+                            frame.rotate(y.atan2(x));
 
-                    let line = canvas::Path::rectangle(Point { x: 0.0, y: -rad }, iced::Size { width: len, height: rad * 2.0 });
-                    frame.fill(&line, Self::WIN_COLOR);
+                            let line = canvas::Path::rectangle(Point { x: 0.0, y: -rad }, iced::Size { width: len, height: rad * 2.0 });
+                            frame.fill(&line, Self::WIN_COLOR);
+                        });
+                    }
+                }
+                _ if self.popping() => {
+                    let shift = self.animations.point_at(&Anim::Pop, self.now).map_or(1.0, |point| point.y);
+                    let col = self.sector as usize;
+
+                    for row in 0..self.game.col_height(col) {
+                        let Some(color) = self.game.grid()[col * rows + row].map(Self::color) else {
+                            continue;
+                        };
+
+                        let (x, y) = ((col + side as usize) as f32, (rows - row) as f32 + shift);
+                        let coin = canvas::Path::rectangle(
+                            offset + Vector { x: x * chunk_size, y: y * chunk_size },
+                            iced::Size { width: chunk_size, height: chunk_size }
+                        );
+
+                        frame.fill(&coin, color);
+                    }
+                }
+                _ if self.blasting() => {
+                    let opacity = self.animations.point_at(&Anim::Blast, self.now).map_or(0.0, |point| point.x);
+                    let col = self.sector as usize;
+                    let row = self.game.col_height(col).saturating_sub(1);
+
+                    if let Some(color) = self.game.grid()[col * rows + row].map(Self::color) {
+                        let (x, y) = ((col + side as usize) as f32, (rows - row) as f32);
+                        let coin = canvas::Path::rectangle(
+                            offset + Vector { x: x * chunk_size, y: y * chunk_size },
+                            iced::Size { width: chunk_size, height: chunk_size }
+                        );
+
+                        frame.fill(&coin, Color { a: opacity, ..color });
+                    }
                 }
                 _ => {
                     let coin_rad = chunk_size * Self::COIN_SIZE * 0.5;
 
-                    let coin_coef = self.animation.point_at(self.now) - Point::ORIGIN;
+                    let coin_coef = self.coin_at(self.now) - Point::ORIGIN;
                     let coin_vec = coin_coef * chunk_size;
                     let coin_pos = offset + coin_vec;
 
                     let coin = canvas::Path::circle(coin_pos, coin_rad);
-                    let coin_color = match self.game.player_turn() {
-                        Player::Yellow => Self::YELLOW_PLAYER,
-                        Player::Red => Self::RED_PLAYER,
-                    };
-
-                    frame.fill(&coin, coin_color);
+                    frame.fill(&coin, Self::color(self.game.player_turn()));
                 }
             }
         });
@@ -367,10 +944,10 @@ impl canvas::Program<Message> for Board {
             let Point { x: ox, y: oy } = &offset;
             use std::f32::consts::TAU;
 
-            for mut j in (0..Game::ROW).map(as_f32) {
+            for mut j in (0..rows).map(as_f32) {
                 j += 1.5;
 
-                for mut i in (0..Game::COL).map(as_f32) {
+                for mut i in (0..cols).map(as_f32) {
                     i += 0.5;
 
                     let tile = canvas::Path::new(|builder| {
@@ -410,8 +987,7 @@ impl canvas::Program<Message> for Board {
         match event {
             canvas::Event::Mouse(ms_event) => match ms_event {
                 iced::mouse::Event::CursorMoved { position } => {
-                    let (offset, chunk_size) = offset_and_chunk_size(bounds.size());
-                    let sector = ((position.x - offset.x) / chunk_size).clamp(0.0, Game::COL as f32 - 1.0) as u8;
+                    let sector = self.sector_at(position.x, bounds);
 
                     if sector != self.sector {
                         message = Some(Message::Slide(sector));
@@ -419,10 +995,12 @@ impl canvas::Program<Message> for Board {
                 },
                 iced::mouse::Event::ButtonPressed(iced::mouse::Button::Left) => {
                     if let canvas::Cursor::Available(position) = cursor {
-                        let (offset, chunk_size) = offset_and_chunk_size(bounds.size());
-                        let sector = ((position.x - offset.x) / chunk_size).clamp(0.0, Game::COL as f32 - 1.0) as u8;
-
-                        message = Some(Message::Play(sector));
+                        message = Some(Message::Play(self.sector_at(position.x, bounds)));
+                    }
+                }
+                iced::mouse::Event::ButtonPressed(iced::mouse::Button::Right) => {
+                    if let (canvas::Cursor::Available(position), Some((flag, _))) = (cursor, self.special_move()) {
+                        message = Some(Message::Play(self.sector_at(position.x, bounds) | flag));
                     }
                 }
                 _ => (),
@@ -430,6 +1008,14 @@ impl canvas::Program<Message> for Board {
             canvas::Event::Keyboard(kb_event) => {
                 if let iced::keyboard::Event::KeyPressed { key_code: iced::keyboard::KeyCode::R, .. } = kb_event {
                     message = Some(Message::Restart);
+                } else if let iced::keyboard::Event::KeyPressed { key_code: iced::keyboard::KeyCode::U, .. } = kb_event {
+                    message = Some(Message::Undo);
+                } else if let iced::keyboard::Event::KeyPressed { key_code: iced::keyboard::KeyCode::Escape, .. } = kb_event {
+                    message = Some(Message::Resign);
+                } else if let iced::keyboard::Event::KeyPressed { key_code: iced::keyboard::KeyCode::Tab, .. } = kb_event {
+                    message = Some(Message::Special);
+                } else if let iced::keyboard::Event::KeyPressed { key_code: iced::keyboard::KeyCode::T, .. } = kb_event {
+                    message = Some(Message::Threats);
                 }
             }
             canvas::Event::Touch(_) => (),
@@ -439,6 +1025,18 @@ impl canvas::Program<Message> for Board {
     }
 }
 
+#[derive(Clone, Copy, PartialEq, Eq, Hash)]
+enum Anim {
+    Coin,
+    Drop,
+    Pop,
+    Blast,
+    WinLine,
+    WinPulse,
+    ScorePopup,
+    FadeOut,
+}
+
 #[derive(Clone, Copy, PartialEq, Eq)]
 enum BoardState {
     Initialize,
@@ -449,18 +1047,21 @@ enum BoardState {
     Playing,
     Win,
     Finished,
+    Forfeit,
 }
 
 impl BoardState {
     fn new_action(&mut self, action: Self) -> bool {
         match (&self, action) {
-            (Self::Initialize, Self::InitThenPlay | Self::Waiting) => (),
+            (Self::Initialize, Self::InitThenPlay | Self::Waiting | Self::Playing) => (),
             (Self::InitThenPlay, Self::Sliding | Self::SlideThenPlay | Self::Playing) => (),
             (Self::Waiting, Self::Sliding | Self::SlideThenPlay | Self::Playing) => (),
-            (Self::Sliding, Self::Waiting | Self::Sliding | Self::SlideThenPlay) => (),
+            (Self::Sliding, Self::Waiting | Self::Sliding | Self::SlideThenPlay | Self::Playing) => (),
             (Self::SlideThenPlay, Self::Playing | Self::Waiting) => (),
             (Self::Playing, Self::Initialize | Self::Win | Self::Finished) => (),
-            (Self::Win | Self::Finished, Self::Initialize) => (),
+            (Self::Win | Self::Finished | Self::Forfeit, Self::Initialize) => (),
+            (Self::Initialize | Self::InitThenPlay | Self::Waiting |
+             Self::Sliding | Self::SlideThenPlay | Self::Playing, Self::Forfeit) => (),
             _ => return false,
         };
 
@@ -469,7 +1070,7 @@ impl BoardState {
     }
 
     fn finished(&self) -> bool {
-        *self == BoardState::Win || *self == BoardState::Finished
+        matches!(self, BoardState::Win | BoardState::Finished | BoardState::Forfeit)
     }
 }
 
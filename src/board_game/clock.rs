@@ -0,0 +1,108 @@
+use super::Player;
+
+use std::time::{Duration, Instant};
+
+#[derive(Clone, Copy)]
+pub struct Clock {
+    remaining: [Duration; 4],
+
+    base: Duration,
+    increment: Duration,
+    last_tick: Option<Instant>,
+}
+
+impl Clock {
+    pub fn new(base: Duration, increment: Duration) -> Self {
+        Self {
+            remaining: [base; 4],
+            base, increment,
+            last_tick: None,
+        }
+    }
+
+    pub fn remaining(&self, player: Player) -> Duration {
+        self.remaining[player.index()]
+    }
+
+    /// Consume the time elapsed since the last tick on the `active` player
+    /// clock. Return the player whose flag fell, if any.
+    pub fn tick(&mut self, now: Instant, active: Option<Player>) -> Option<Player> {
+        let last_tick = self.last_tick.replace(now);
+        let (Some(player), Some(last_tick)) = (active, last_tick) else {
+            return None;
+        };
+
+        let remaining = self.remaining_mut(player);
+        *remaining = remaining.saturating_sub(now.saturating_duration_since(last_tick));
+
+        if remaining.is_zero() { Some(player) } else { None }
+    }
+
+    /// Take the remaining times of another clock, such as a broadcast one.
+    pub fn sync(&mut self, red: Duration, yellow: Duration) {
+        self.remaining[Player::Red.index()] = red;
+        self.remaining[Player::Yellow.index()] = yellow;
+    }
+
+    pub fn press(&mut self, player: Player) {
+        let increment = self.increment;
+        *self.remaining_mut(player) += increment;
+    }
+
+    pub fn reset(&mut self) {
+        *self = Self::new(self.base, self.increment);
+    }
+
+    fn remaining_mut(&mut self, player: Player) -> &mut Duration {
+        &mut self.remaining[player.index()]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SECOND: Duration = Duration::from_secs(1);
+
+    #[test]
+    fn ticks_run_the_active_clock_and_presses_add_the_increment() {
+        let mut clock = Clock::new(10 * SECOND, 2 * SECOND);
+        let start = Instant::now();
+
+        // the first tick only starts counting
+        assert_eq!(clock.tick(start, Some(Player::Red)), None);
+        assert_eq!(clock.tick(start + 3 * SECOND, Some(Player::Red)), None);
+        assert_eq!(clock.remaining(Player::Red), 7 * SECOND);
+
+        clock.press(Player::Red);
+        assert_eq!(clock.remaining(Player::Red), 9 * SECOND);
+
+        // time passing without an active player is lost to nobody
+        assert_eq!(clock.tick(start + 5 * SECOND, None), None);
+        assert_eq!(clock.tick(start + 6 * SECOND, Some(Player::Yellow)), None);
+        assert_eq!(clock.remaining(Player::Yellow), 9 * SECOND);
+        assert_eq!(clock.remaining(Player::Red), 9 * SECOND);
+    }
+
+    #[test]
+    fn the_flag_falls_at_zero() {
+        let mut clock = Clock::new(2 * SECOND, Duration::ZERO);
+        let start = Instant::now();
+
+        clock.tick(start, Some(Player::Yellow));
+        assert_eq!(clock.tick(start + SECOND, Some(Player::Yellow)), None);
+        assert_eq!(clock.tick(start + 5 * SECOND, Some(Player::Yellow)), Some(Player::Yellow));
+        assert_eq!(clock.remaining(Player::Yellow), Duration::ZERO);
+    }
+
+    #[test]
+    fn sync_and_reset_replace_the_remaining_times() {
+        let mut clock = Clock::new(10 * SECOND, SECOND);
+        clock.sync(4 * SECOND, 6 * SECOND);
+        assert_eq!((clock.remaining(Player::Red), clock.remaining(Player::Yellow)), (4 * SECOND, 6 * SECOND));
+
+        clock.press(Player::Green);
+        clock.reset();
+        assert!(Player::ALL.iter().all(|&player| clock.remaining(player) == 10 * SECOND));
+    }
+}
@@ -1,145 +1,996 @@
-#[derive(Clone, Copy, PartialEq, Eq)]
+use super::rules::{Rules, Tally, STANDARD};
+use super::Moves;
+
+use std::fmt;
+
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
 pub enum Player {
-    Red, Yellow,
+    Red, Yellow, Green, Blue,
+}
+
+impl Player {
+    /// Every colour, in the order of per player arrays.
+    pub const ALL: [Player; 4] = [Player::Red, Player::Yellow, Player::Green, Player::Blue];
+
+    /// The other player of a two player game, green and blue facing each
+    /// other when they play alone.
+    pub fn opponent(self) -> Self {
+        match self {
+            Player::Red => Player::Yellow,
+            Player::Yellow => Player::Red,
+            Player::Green => Player::Blue,
+            Player::Blue => Player::Green,
+        }
+    }
+
+    /// Position of the player in per player arrays.
+    pub fn index(self) -> usize {
+        match self {
+            Player::Red => 0,
+            Player::Yellow => 1,
+            Player::Green => 2,
+            Player::Blue => 3,
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Player::Red => "red",
+            Player::Yellow => "yellow",
+            Player::Green => "green",
+            Player::Blue => "blue",
+        }
+    }
 }
 
+/// Flag of a move code taking the bottom coin out of a column instead of
+/// dropping one.
+pub const POP: u8 = 0x80;
+/// Flag of a move code dropping an anvil, in Power Up.
+pub const ANVIL: u8 = 0x40;
+/// Flag of a move code dropping a bomb, in Power Up.
+pub const BOMB: u8 = 0x20;
+
+/// Letters written before the column of flagged moves.
+const FLAGS: [(u8, char); 3] = [(POP, 'p'), (ANVIL, 'a'), (BOMB, 'b')];
+
+/// Random keys of a coin of each player in each cell, then of each
+/// player to move, xored together into the hash of a position.
+const ZOBRIST: [[u64; 4]; Game::CELLS + 1] = {
+    // splitmix64, a fixed seed keeps hashes stable between runs
+    let mut keys = [[0; 4]; Game::CELLS + 1];
+    let mut state: u64 = 0x5eed_c01e_c7f0;
+    let mut index = 0;
+
+    while index < keys.len() * 4 {
+        state = state.wrapping_add(0x9e37_79b9_7f4a_7c15);
+        let mut key = state;
+        key = (key ^ (key >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        key = (key ^ (key >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        keys[index / 4][index % 4] = key ^ (key >> 31);
+        index += 1;
+    }
+
+    keys
+};
+
+/// Outcome of a move.
+#[derive(PartialEq, Eq, Hash, Debug)]
 pub enum PlayResult {
-    Win([u8; 4]),
+    /// Every line created by the winning move, a single move can complete
+    /// up to four of them at once.
+    Win(Vec<Line>),
+    /// The move only completed lines of other players, the owner of the
+    /// first line wins.
+    Lose(Vec<Line>),
     Error, Pass,
 }
 
+/// Aligned cells of a winning line as `(col, row)`, from one end to the
+/// other. Cells of side columns have a column outside of the grid.
+#[derive(PartialEq, Eq, Hash, Debug)]
+pub struct Line {
+    player: Player,
+    cells: Vec<(i8, i8)>,
+}
+
+impl Line {
+    /// Owner of the coins of the line.
+    pub fn player(&self) -> Player {
+        self.player
+    }
+
+    pub fn cells(&self) -> &[(i8, i8)] {
+        &self.cells
+    }
+
+    pub fn start(&self) -> (i8, i8) {
+        self.cells[0]
+    }
+
+    pub fn end(&self) -> (i8, i8) {
+        self.cells[self.cells.len() - 1]
+    }
+}
+
+/// Players around the grid in turn order, with the seats of the player
+/// who started and of the player to move.
 #[derive(Clone, Copy)]
-enum Memory {
-    RedRed, RedYellow,
-    YellowRed, YellowYellow,
+struct Seating {
+    order: [Player; 4],
+    count: u8,
+    first: u8,
+    turn: u8,
+}
+
+impl Seating {
+    fn next(self, seat: u8) -> u8 {
+        (seat + 1) % self.count
+    }
+
+    fn previous(self, seat: u8) -> u8 {
+        (seat + self.count - 1) % self.count
+    }
+
+    fn seat(self, player: Player) -> Option<u8> {
+        self.order[..self.count as usize].iter().position(|&seated| seated == player).map(|seat| seat as u8)
+    }
 }
 
 #[derive(Clone, Copy)]
 pub struct Game {
-    player_turn: Memory,
-    grid: [Option<Player>; Self::ROW * Self::COL],
+    seating: Seating,
+    /// Columns and rows of the grid.
+    size: (u8, u8),
+    /// Cells as `col * rows + row`, those past the size are left empty.
+    grid: [Option<Player>; Self::CELLS],
+    /// Keys of the coins of the grid, see `zobrist`.
+    zobrist: u64,
+    /// Keys of the coins of the mirrored grid.
+    mirror_zobrist: u64,
+    rules: &'static dyn Rules,
+    tally: Tally,
 }
 
 impl Game {
+    /// Size of the standard grid, the only one played over the network
+    /// and by engines.
     pub const COL: usize = 7;
     pub const ROW: usize = 6;
+    /// Largest grid, one column digit per move in the notation.
+    pub const MAX_COL: usize = 9;
+    pub const MAX_ROW: usize = 9;
+    /// Smallest side of a grid.
+    pub const MIN_SIDE: usize = 4;
+    const CELLS: usize = Self::MAX_COL * Self::MAX_ROW;
+
+    /// Two player game, `first` starting against red, or against yellow
+    /// when red starts. Other players sit down with `seating`.
+    pub fn new(first: Player) -> Self {
+        let mut game = Self::default();
+        game.seating.order.swap(0, first.index());
+        game.seating.order[1..].sort_by_key(|player| player.index());
+
+        game
+    }
+
+    /// Seat 2 to 4 distinct players, to play in the order given. The
+    /// starting player keeps the first move if seated, otherwise the first
+    /// of `order` has it.
+    pub fn seating(mut self, order: &[Player]) -> Self {
+        assert!((2..=4).contains(&order.len()), "2 to 4 players are seated");
+
+        let first = self.seating.order[self.seating.first as usize];
+        self.seating.order[..order.len()].copy_from_slice(order);
+        self.seating.count = order.len() as u8;
+        self.seating.first = self.seating.seat(first).unwrap_or(0);
+        self.seating.turn = self.seating.first;
+
+        self
+    }
+
+    /// Seated players in turn order.
+    pub fn players(&self) -> &[Player] {
+        &self.seating.order[..self.seating.count as usize]
+    }
+
+    /// Player moving after `player`.
+    pub fn next_player(&self, player: Player) -> Player {
+        let seat = self.seating.seat(player).unwrap_or(0);
+        self.seating.order[self.seating.next(seat) as usize]
+    }
+
+    /// The only opponent of `player`, in a two player game.
+    pub fn opponent_of(&self, player: Player) -> Option<Player> {
+        match self.players() {
+            [first, second] if *first == player => Some(*second),
+            [first, second] if *second == player => Some(*first),
+            _ => None,
+        }
+    }
+
+    /// Play on a grid of `cols` by `rows`, emptying it.
+    pub fn grid_size(mut self, cols: usize, rows: usize) -> Self {
+        assert!((Self::MIN_SIDE..=Self::MAX_COL).contains(&cols) && (Self::MIN_SIDE..=Self::MAX_ROW).contains(&rows), "unsupported grid size");
+
+        self.size = (cols as u8, rows as u8);
+        self.grid = [None; Self::CELLS];
+        self.zobrist = 0;
+        self.mirror_zobrist = 0;
+        self
+    }
+
+    pub fn cols(&self) -> usize {
+        self.size.0 as usize
+    }
+
+    pub fn rows(&self) -> usize {
+        self.size.1 as usize
+    }
+
+    /// The grid is 7 by 6.
+    pub fn standard_size(&self) -> bool {
+        self.size == (Self::COL as u8, Self::ROW as u8)
+    }
+
+    /// Rebuild a two player game of the standard grid from its cells and
+    /// the player to move. Fail when a coin floats over an empty cell or
+    /// the coin counts cannot alternate.
+    pub fn from_grid(grid: [Option<Player>; Self::ROW * Self::COL], turn: Player) -> Result<Self, String> {
+        for col in grid.chunks(Self::ROW) {
+            if col.iter().skip_while(|cell| cell.is_some()).any(Option::is_some) {
+                return Err(String::from("a coin is floating over an empty cell"));
+            }
+        }
+
+        let count = |player| grid.iter().filter(|&&cell| cell == Some(player)).count();
+        let (turn_count, other_count) = (count(turn), count(turn.opponent()));
+
+        // the player to move started if both played the same number of coins
+        let first = if turn_count == other_count {
+            turn
+        } else if turn_count + 1 == other_count {
+            turn.opponent()
+        } else {
+            return Err(String::from("coin counts do not match the player to move"));
+        };
+
+        let mut game = Self::new(first).seating(&[first, first.opponent()]);
+        game.grid[..grid.len()].copy_from_slice(&grid);
+        (0..grid.len()).for_each(|index| game.toggle(index));
+        if turn != first {
+            game.pass_turn();
+        }
+
+        Ok(game)
+    }
+
+    /// Play by `rules` instead of the standard ones.
+    pub fn variant(mut self, rules: &'static dyn Rules) -> Self {
+        self.rules = rules;
+        self.tally = rules.setup();
+        self
+    }
+
+    pub fn rules(&self) -> &'static dyn Rules {
+        self.rules
+    }
+
+    pub fn tally(&self) -> Tally {
+        self.tally
+    }
+
+    /// Play a sequence of 1-based column digits such as `"4453"`, special
+    /// moves being preceded by their letter as in `"44p4"`. Fail on an
+    /// unknown column, an illegal move or a move ending the game.
+    pub fn play_notation(&mut self, moves: &str) -> Result<(), String> {
+        for (index, text) in split_notation(moves).enumerate() {
+            let Some(code) = parse_move(text) else {
+                return Err(format!("invalid move '{text}' at move {}", index + 1));
+            };
+
+            match self.play(code) {
+                PlayResult::Pass if self.stalemate() => return Err(format!("no move is left after move {}", index + 1)),
+                PlayResult::Pass => (),
+                PlayResult::Error => return Err(format!("move {text} is illegal at move {}", index + 1)),
+                PlayResult::Win(_) | PlayResult::Lose(_) => return Err(format!("the game is over at move {}", index + 1)),
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Play the move `code`, a column possibly flagged with a special move.
+    pub fn play(&mut self, code: u8) -> PlayResult {
+        self.rules.play(self, code)
+    }
+
+    /// Take back the move `code`, which must be the last one played. Fail
+    /// when the rules cannot take it back.
+    pub fn undo(&mut self, code: u8) -> bool {
+        self.rules.undo(self, code)
+    }
+
+    /// Every move the player to move may play.
+    pub fn legal_moves(&self) -> Moves {
+        self.rules.legal_moves(self)
+    }
+
+    /// Moves winning the game at once for the player to move.
+    pub fn winning_moves(&self) -> Moves {
+        self.legal_moves()
+            .filter(|&code| {
+                let mut child = *self;
+                matches!(child.play(code), PlayResult::Win(_))
+            })
+            .collect()
+    }
+
+    /// Moves taking away a winning move of the next player, as if it were
+    /// already their turn.
+    pub fn blocking_moves(&self) -> Moves {
+        let mut next = *self;
+        next.pass_turn();
+
+        self.legal_moves().intersection(next.winning_moves())
+    }
+
+    /// Moves after which the next player cannot win at once, winning moves
+    /// included.
+    pub fn non_losing_moves(&self) -> Moves {
+        let player = self.player_turn();
+
+        self.legal_moves()
+            .filter(|&code| {
+                let mut child = *self;
+
+                match child.play(code) {
+                    PlayResult::Win(_) => true,
+                    PlayResult::Pass => child.player_turn() == player || child.winning_moves().is_empty(),
+                    PlayResult::Lose(_) | PlayResult::Error => false,
+                }
+            })
+            .collect()
+    }
+
+    /// The player to move has no legal move left, the game is a draw.
+    pub fn stalemate(&self) -> bool {
+        self.legal_moves().is_empty()
+    }
+
+    /// Sequences of `depth` moves from the position, leaving out those of
+    /// games ending before the last move.
+    pub fn perft(&self, depth: u8) -> u64 {
+        let moves = self.legal_moves();
+
+        match depth {
+            0 => 1,
+            1 => moves.len() as u64,
+            _ => moves
+                .map(|code| {
+                    let mut child = *self;
+
+                    match child.play(code) {
+                        PlayResult::Pass => child.perft(depth - 1),
+                        _ => 0,
+                    }
+                })
+                .sum(),
+        }
+    }
 
     pub fn play_col(&mut self, col: usize) -> PlayResult {
+        match u8::try_from(col) {
+            Ok(col) => self.play(col),
+            Err(_) => PlayResult::Error,
+        }
+    }
+
+    /// Take back the last coin played in `col`. Fail on an empty column.
+    pub fn undo_col(&mut self, col: usize) -> bool {
+        u8::try_from(col).is_ok_and(|col| self.undo(col))
+    }
+
+    /// Drop a coin of the player to move in `col` and pass the turn.
+    pub(super) fn drop_coin(&mut self, col: usize) -> PlayResult {
         let height = self.col_height(col);
-        if height == Self::ROW { return PlayResult::Error; }
+        if height == self.rows() { return PlayResult::Error; }
 
-        let index = col * Self::ROW;
-        self.grid[index + height] = Some(self.player_turn.into());
-        let connect = self.connected(col as i8, height as i8);
+        let player = self.player_turn();
+        self.place(col);
+        self.pass_turn();
 
-        self.player_turn = match self.player_turn {
-            Memory::RedRed => Memory::RedYellow,
-            Memory::RedYellow => Memory::RedRed,
-            Memory::YellowRed => Memory::YellowYellow,
-            Memory::YellowYellow => Memory::YellowRed,
-        };
+        let lines = self.lines(col as i8, height as i8, player);
 
-        connect
+        if lines.is_empty() {
+            PlayResult::Pass
+        } else {
+            PlayResult::Win(lines)
+        }
     }
 
-    fn connected(&self, col: i8, row: i8) -> PlayResult {
-        const DIRS: [(i8, i8); 4] = [(0, -1), (1, 1), (1, 0), (1, -1)];
-        let target = Some(self.player_turn.into());
+    /// Take back the top coin of `col` and the turn.
+    pub(super) fn take_back(&mut self, col: usize) -> bool {
+        let height = self.col_height(col);
+        if height == 0 { return false; }
 
-        for (x, y) in DIRS {
-            let (mut forward, mut backward) = (0, 0);
+        self.toggle(col * self.rows() + height - 1);
+        self.grid[col * self.rows() + height - 1] = None;
+        self.seating.turn = self.seating.previous(self.seating.turn);
 
-            loop {
-                if backward == 3 { break; }
-                backward += 1;
+        true
+    }
+
+    /// Take the bottom coin of `col` out and pass the turn. Lines may
+    /// appear for several players: the player popping wins when one of
+    /// them is theirs.
+    pub(super) fn pop_coin(&mut self, col: usize) -> PlayResult {
+        let player = self.player_turn();
+        self.remove(col, 0);
+        self.pass_turn();
 
-                let actual_col = (col - x * backward) as usize;
-                let actual_row = (row - y * backward) as usize;
+        let (mut mine, mut theirs) = (Vec::new(), Vec::new());
 
-                if !(0..Self::COL).contains(&actual_col) ||
-                   !(0..Self::ROW).contains(&actual_row) ||
-                   self.grid[actual_col * Self::ROW + actual_row] != target
-                {
-                    backward -= 1;
-                    break;
+        for row in 0..self.col_height(col) {
+            let Some(owner) = self.grid[col * self.rows() + row] else { continue; };
+            let lines = if owner == player { &mut mine } else { &mut theirs };
+
+            for line in self.lines(col as i8, row as i8, owner) {
+                if !lines.contains(&line) {
+                    lines.push(line);
                 }
             }
+        }
 
-            loop {
-                if forward == 3 - backward { break; }
-                forward += 1;
+        if !mine.is_empty() {
+            PlayResult::Win(mine)
+        } else if !theirs.is_empty() {
+            PlayResult::Lose(theirs)
+        } else {
+            PlayResult::Pass
+        }
+    }
 
-                let actual_col = (col + x * forward) as usize;
-                let actual_row = (row + y * forward) as usize;
+    /// Put back the coin popped out of `col` by the previous player.
+    pub(super) fn unpop(&mut self, col: usize) -> bool {
+        let (index, rows) = (col * self.rows(), self.rows());
+        if self.col_full(col) { return false; }
 
-                if !(0..Self::COL).contains(&actual_col) ||
-                   !(0..Self::ROW).contains(&actual_row) ||
-                   self.grid[actual_col * Self::ROW + actual_row] != target
-                {
-                    forward -= 1;
-                    break;
-                }
+        self.seating.turn = self.seating.previous(self.seating.turn);
+        self.toggle_col(col);
+        self.grid.copy_within(index..index + rows - 1, index + 1);
+        self.grid[index] = Some(self.player_turn());
+        self.toggle_col(col);
+
+        true
+    }
+
+    /// Put a coin of the player to move on top of `col`, which is not full.
+    pub(super) fn place(&mut self, col: usize) {
+        let height = self.col_height(col);
+        self.grid[col * self.rows() + height] = Some(self.player_turn());
+        self.toggle(col * self.rows() + height);
+    }
+
+    /// Take the coin at `row` of `col` out, the coins above fall by one row.
+    pub(super) fn remove(&mut self, col: usize, row: usize) {
+        let (index, rows) = (col * self.rows(), self.rows());
+        self.toggle_col(col);
+        self.grid.copy_within(index + row + 1..index + rows, index + row);
+        self.grid[index + rows - 1] = None;
+        self.toggle_col(col);
+    }
+
+    /// Xor the key of the coin at `index`, if any, into the hash.
+    fn toggle(&mut self, index: usize) {
+        if let Some(player) = self.grid[index] {
+            let (col, row) = (index / self.rows(), index % self.rows());
+
+            self.zobrist ^= ZOBRIST[index][player.index()];
+            self.mirror_zobrist ^= ZOBRIST[self.mirror_column(col) * self.rows() + row][player.index()];
+        }
+    }
+
+    fn toggle_col(&mut self, col: usize) {
+        (col * self.rows()..(col + 1) * self.rows()).for_each(|index| self.toggle(index));
+    }
+
+    pub(super) fn pass_turn(&mut self) {
+        self.seating.turn = self.seating.next(self.seating.turn);
+    }
+
+    pub(super) fn tally_mut(&mut self) -> &mut Tally {
+        &mut self.tally
+    }
+
+    /// Lines long enough to win made of coins of `player` going through
+    /// `(col, row)`.
+    pub(super) fn lines(&self, col: i8, row: i8, player: Player) -> Vec<Line> {
+        const DIRS: [(i8, i8); 4] = [(0, -1), (1, 1), (1, 0), (1, -1)];
+        let target = Some(player);
+        let length = self.rules.connect() as i8;
+        let mut lines = Vec::new();
+
+        let owned = |col: i8, row: i8| {
+            (0..self.rows() as i8).contains(&row) && self.cell(col, row as usize) == target
+        };
+
+        for (x, y) in DIRS {
+            let (mut forward, mut backward) = (0, 0);
+
+            while owned(col - x * (backward + 1), row - y * (backward + 1)) {
+                backward += 1;
             }
 
-            if forward + backward == 3 {
-                return PlayResult::Win([
-                    (col - x * backward) as u8,
-                    (row - y * backward) as u8,
-                    (col + x * forward) as u8,
-                    (row + y * forward) as u8,
-                ]);
+            while owned(col + x * (forward + 1), row + y * (forward + 1)) {
+                forward += 1;
+            }
+
+            if forward + backward + 1 >= length {
+                let cells = (-backward..=forward)
+                    .map(|step| (col + x * step, row + y * step))
+                    .collect();
+
+                lines.push(Line { player, cells });
             }
         }
 
-        PlayResult::Pass
+        lines
+    }
+
+    /// The coin at `(col, row)` is part of a winning line.
+    pub fn connected(&self, col: usize, row: usize) -> bool {
+        self.grid[col * self.rows() + row].is_some_and(|player| !self.lines(col as i8, row as i8, player).is_empty())
+    }
+
+    /// A coin of `player` at the empty `(col, row)` would complete a line.
+    pub fn completes_line(&self, col: usize, row: usize, player: Player) -> bool {
+        self.grid[col * self.rows() + row].is_none() && !self.lines(col as i8, row as i8, player).is_empty()
+    }
+
+    /// Coin at `(col, row)`, side columns included.
+    pub fn cell(&self, col: i8, row: usize) -> Option<Player> {
+        let side = self.rules.side_columns() as i8;
+
+        if (0..self.cols() as i8).contains(&col) {
+            self.grid[col as usize * self.rows() + row]
+        } else if (-side..self.cols() as i8 + side).contains(&col) {
+            self.rules.side_cell(col, row)
+        } else {
+            None
+        }
     }
 
     pub fn col_height(&self, col: usize) -> usize {
-        let index = col as usize * Self::ROW;
-        let chunk = &self.grid[index..index + Self::ROW];
+        let index = col * self.rows();
+        let chunk = &self.grid[index..index + self.rows()];
 
         chunk.iter().take_while(|cell| cell.is_some()).count()
     }
 
     pub fn col_full(&self, col: usize) -> bool {
-        self.col_height(col) == Self::ROW
+        self.col_height(col) == self.rows()
     }
 
-    pub fn grid(&self) -> [Option<Player>; Self::ROW * Self::COL] {
-        self.grid
+    /// Cells as `col * rows + row`.
+    pub fn grid(&self) -> &[Option<Player>] {
+        &self.grid[..self.cols() * self.rows()]
     }
 
     pub fn player_turn(&self) -> Player {
-        self.player_turn.into()
+        self.seating.order[self.seating.turn as usize]
+    }
+
+    /// Zobrist hash of the coins and the player to move, kept up to date
+    /// move after move. The variant and its tally are left out.
+    pub fn zobrist(&self) -> u64 {
+        self.zobrist ^ ZOBRIST[Self::CELLS][self.player_turn().index()]
+    }
+
+    /// Position with the columns in reverse order, the same one for
+    /// symmetric rules.
+    pub fn mirrored(&self) -> Self {
+        let mut mirrored = *self;
+
+        for col in 0..self.cols() {
+            let (index, mirror) = (col * self.rows(), self.mirror_column(col) * self.rows());
+            mirrored.grid[mirror..mirror + self.rows()].copy_from_slice(&self.grid[index..index + self.rows()]);
+        }
+
+        mirrored.tally.pending = self.tally.pending.map(|code| self.mirror_move(code));
+        std::mem::swap(&mut mirrored.zobrist, &mut mirrored.mirror_zobrist);
+        mirrored
+    }
+
+    /// The one of the position and its mirror with the lowest hash, and
+    /// whether it is the mirror. Moves of a mirrored representative map
+    /// back with `Game::mirror_move`. Rules without symmetry keep the position.
+    pub fn canonical(&self) -> (Self, bool) {
+        match self.rules.symmetric() && self.mirror_zobrist < self.zobrist {
+            true => (self.mirrored(), true),
+            false => (*self, false),
+        }
+    }
+
+    /// Hash of the canonical position, the same for a position and its
+    /// mirror.
+    pub fn canonical_zobrist(&self) -> u64 {
+        let grid = match self.rules.symmetric() {
+            true => self.zobrist.min(self.mirror_zobrist),
+            false => self.zobrist,
+        };
+
+        grid ^ ZOBRIST[Self::CELLS][self.player_turn().index()]
+    }
+
+    /// Column facing `col` across the middle of the grid.
+    pub fn mirror_column(&self, col: usize) -> usize {
+        self.cols() - 1 - col
+    }
+
+    /// Move `code` played in the mirrored position, its flags kept.
+    /// Mirroring twice gives `code` back.
+    pub fn mirror_move(&self, code: u8) -> u8 {
+        self.mirror_column(move_column(code)) as u8 | (code & (POP | ANVIL | BOMB))
     }
 
     pub fn grid_full(&self) -> bool {
-        self.grid.iter().all(Option::is_some)
+        self.grid().iter().all(Option::is_some)
     }
 
+    /// Empty the grid, the next seat starting.
     pub fn restart(&mut self) {
-        self.grid = [None; Self::COL * Self::ROW];
-        self.player_turn = match self.player_turn {
-            Memory::RedRed | Memory::RedYellow => Memory::YellowYellow,
-            Memory::YellowRed | Memory::YellowYellow => Memory::RedRed,
-        }
+        self.grid = [None; Self::CELLS];
+        self.zobrist = 0;
+        self.mirror_zobrist = 0;
+        self.tally = self.rules.setup();
+        self.seating.first = self.seating.next(self.seating.first);
+        self.seating.turn = self.seating.first;
     }
 }
 
 impl Default for Game {
     fn default() -> Self {
         Self {
-            player_turn: Memory::RedRed,
-            grid: [None; Self::COL * Self::ROW],
+            seating: Seating { order: Player::ALL, count: 2, first: 0, turn: 0 },
+            size: (Self::COL as u8, Self::ROW as u8),
+            grid: [None; Self::CELLS],
+            zobrist: 0,
+            mirror_zobrist: 0,
+            rules: &STANDARD,
+            tally: Tally::default(),
+        }
+    }
+}
+
+/// Unseated colours left at the end of `order` are not compared.
+impl PartialEq for Seating {
+    fn eq(&self, other: &Self) -> bool {
+        self.order[..self.count as usize] == other.order[..other.count as usize]
+            && (self.first, self.turn) == (other.first, other.turn)
+    }
+}
+
+impl Eq for Seating {}
+
+impl PartialEq for Game {
+    fn eq(&self, other: &Self) -> bool {
+        self.size == other.size
+            && self.grid == other.grid
+            && self.seating == other.seating
+            && self.tally == other.tally
+            && self.rules.name() == other.rules.name()
+    }
+}
+
+impl Eq for Game {}
+
+impl std::hash::Hash for Game {
+    fn hash<H: std::hash::Hasher>(&self, state: &mut H) {
+        state.write_u64(self.zobrist());
+    }
+}
+
+impl fmt::Debug for Game {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Game")
+            .field("rules", &self.rules.name())
+            .field("players", &self.players())
+            .field("player_turn", &self.player_turn())
+            .field("tally", &self.tally)
+            .field("grid", &self.to_string())
+            .finish()
+    }
+}
+
+/// Rows from top to bottom with the initial of each coin, then the
+/// column numbers and the player to move.
+impl fmt::Display for Game {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let side = self.rules.side_columns() as i8;
+
+        for row in (0..self.rows()).rev() {
+            let cells = (-side..self.cols() as i8 + side)
+                .map(|col| self.cell(col, row).map_or('.', |player| player.name().chars().next().unwrap_or('?')))
+                .map(String::from)
+                .collect::<Vec<_>>();
+
+            writeln!(f, "{}", cells.join(" "))?;
+        }
+
+        let numbers = (1..=self.cols()).map(|col| col.to_string()).collect::<Vec<_>>();
+        writeln!(f, "{}{}", "  ".repeat(side as usize), numbers.join(" "))?;
+        write!(f, "{} to play", self.player_turn())
+    }
+}
+
+impl fmt::Display for Player {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+impl fmt::Display for Line {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let ((start_col, start_row), (end_col, end_row)) = (self.start(), self.end());
+        write!(f, "{} from ({start_col}, {start_row}) to ({end_col}, {end_row})", self.player)
+    }
+}
+
+impl fmt::Display for PlayResult {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let lines = |lines: &[Line]| lines.iter().map(Line::to_string).collect::<Vec<_>>().join(", ");
+
+        match self {
+            PlayResult::Win(lines_won) if lines_won.is_empty() => f.write_str("win"),
+            PlayResult::Win(lines_won) => write!(f, "win: {}", lines(lines_won)),
+            PlayResult::Lose(lines_lost) => write!(f, "loss: {}", lines(lines_lost)),
+            PlayResult::Error => f.write_str("illegal move"),
+            PlayResult::Pass => f.write_str("pass"),
         }
     }
 }
 
-impl From<Memory> for Player {
-    fn from(memory: Memory) -> Self {
-        match memory {
-            Memory::RedYellow | Memory::YellowYellow => Player::Yellow,
-            Memory::RedRed | Memory::YellowRed => Player::Red,
+/// Column of the move `code`, without its flags.
+pub fn move_column(code: u8) -> usize {
+    (code & !(POP | ANVIL | BOMB)) as usize
+}
+
+/// Text of the move `code`: its 1-based column, preceded by the letter of
+/// its flag if any.
+pub fn move_notation(code: u8) -> String {
+    let col = move_column(code) + 1;
+
+    match FLAGS.iter().find(|&&(flag, _)| code & flag != 0) {
+        Some((_, letter)) => format!("{letter}{col}"),
+        None => col.to_string(),
+    }
+}
+
+/// Move code of a text such as `4` or `p4`.
+pub fn parse_move(text: &str) -> Option<u8> {
+    let (flag, col) = match FLAGS.iter().find(|&&(_, letter)| text.starts_with(letter)) {
+        Some(&(flag, letter)) => (flag, &text[letter.len_utf8()..]),
+        None => (0, text),
+    };
+
+    match col.parse::<u8>() {
+        Ok(col @ 1..) if col as usize <= Game::MAX_COL => Some((col - 1) | flag),
+        _ => None,
+    }
+}
+
+/// Moves of a notation such as `44p53`, one column digit each.
+pub fn split_notation(moves: &str) -> impl Iterator<Item = &str> {
+    let mut rest = moves;
+
+    std::iter::from_fn(move || {
+        let first = rest.chars().next()?;
+        let length = match first.is_ascii_digit() {
+            true => first.len_utf8(),
+            false => first.len_utf8() + rest[first.len_utf8()..].chars().next().map_or(0, char::len_utf8),
+        };
+
+        let (text, tail) = rest.split_at(length);
+        rest = tail;
+        Some(text)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::board_game::rules::POP_OUT;
+
+    #[test]
+    fn wins_are_taken_and_blocked() {
+        let mut game = Game::default();
+        game.play_notation("112233").unwrap();
+
+        assert_eq!(game.winning_moves(), [3]);
+        assert!(game.blocking_moves().is_empty());
+        assert_eq!(game.non_losing_moves().len(), Game::COL);
+
+        // yellow has to take the cell red would win on
+        let mut game = Game::default();
+        game.play_notation("11223").unwrap();
+
+        assert!(game.winning_moves().is_empty());
+        assert_eq!(game.blocking_moves(), [3]);
+        assert_eq!(game.non_losing_moves(), [3]);
+    }
+
+    /// Move sequences of the standard game at depths 1 to 12.
+    const PERFT: [u64; 12] = [
+        7, 49, 343, 2401, 16807, 117649, 823536, 5673234,
+        39394572, 268031646, 1844590828, 12418296244,
+    ];
+
+    #[test]
+    fn perft_matches_the_reference_counts() {
+        for depth in 1..=8 {
+            assert_eq!(Game::default().perft(depth), PERFT[depth as usize - 1], "depth {depth}");
+        }
+    }
+
+    #[test]
+    #[ignore = "minutes long, run with cargo test --release -- --ignored"]
+    fn deep_perft_matches_the_reference_counts() {
+        for depth in 9..=12 {
+            assert_eq!(Game::default().perft(depth), PERFT[depth as usize - 1], "depth {depth}");
+        }
+    }
+
+    #[test]
+    fn notation_splits_pops() {
+        let moves = split_notation("44p53").collect::<Vec<_>>();
+        assert_eq!(moves, ["4", "4", "p5", "3"]);
+
+        assert_eq!(parse_move("p5"), Some(4 | POP));
+        assert_eq!(parse_move("9"), Some(8));
+        assert_eq!(parse_move("10"), None);
+        assert_eq!(move_notation(4 | POP), "p5");
+    }
+
+    #[test]
+    fn three_players_take_turns_and_rotate_the_start() {
+        let mut game = Game::new(Player::Green).seating(&[Player::Red, Player::Green, Player::Yellow]);
+        assert!(game.player_turn() == Player::Green);
+        assert!(game.opponent_of(Player::Red).is_none());
+
+        game.play_notation("12").unwrap();
+        assert!(game.player_turn() == Player::Red);
+        assert!(game.undo_col(1) && game.player_turn() == Player::Yellow);
+
+        game.restart();
+        assert!(game.player_turn() == Player::Yellow);
+        game.restart();
+        assert!(game.player_turn() == Player::Red);
+
+        game.play_notation("123123123").unwrap();
+        assert!(matches!(game.play(0), PlayResult::Win(lines) if lines[0].player() == Player::Red));
+    }
+
+    #[test]
+    fn transpositions_are_equal_and_hash_alike() {
+        let (mut first, mut second) = (Game::default(), Game::default());
+        first.play_notation("1234").unwrap();
+        second.play_notation("3214").unwrap();
+
+        assert_eq!(first, second);
+        assert_eq!(first.zobrist(), second.zobrist());
+        assert_ne!(first.zobrist(), Game::default().zobrist());
+        assert_eq!(first.zobrist(), Game::from_grid(first.grid().try_into().unwrap(), first.player_turn()).unwrap().zobrist());
+
+        // the hash follows pops and take backs
+        let mut game = Game::default().variant(&POP_OUT);
+        game.play_notation("1121p1").unwrap();
+
+        assert!(game.undo(POP) && game.undo_col(0));
+        let mut replayed = Game::default().variant(&POP_OUT);
+        replayed.play_notation("112").unwrap();
+        assert_eq!(game.zobrist(), replayed.zobrist());
+    }
+
+    #[test]
+    fn mirrored_positions_share_a_canonical_form() {
+        let (mut left, mut right) = (Game::default().variant(&POP_OUT), Game::default().variant(&POP_OUT));
+        left.play_notation("1123p1").unwrap();
+        right.play_notation("7765p7").unwrap();
+
+        assert_eq!(left.mirrored(), right);
+        assert_eq!(left.mirrored().zobrist(), right.zobrist());
+        assert_eq!(left.canonical_zobrist(), right.canonical_zobrist());
+
+        let ((left, left_mirrored), (right, right_mirrored)) = (left.canonical(), right.canonical());
+        assert_eq!(left, right);
+        assert_ne!(left_mirrored, right_mirrored);
+
+        assert_eq!(left.mirror_move(1 | POP), 5 | POP);
+        assert_eq!(left.mirror_move(left.mirror_move(2)), 2);
+
+        // the side columns of five in a row are not symmetric
+        let mut five = Game::default().variant(&crate::board_game::rules::FIVE_IN_A_ROW);
+        five.play_notation("1").unwrap();
+        assert!(!five.canonical().1);
+    }
+
+    #[test]
+    fn games_and_results_are_displayed() {
+        let mut game = Game::default();
+        game.play_notation("444").unwrap();
+
+        let shown = game.to_string();
+        assert!(shown.ends_with(". . . r . . .\n1 2 3 4 5 6 7\nyellow to play"), "{shown}");
+
+        let mut game = Game::default();
+        game.play_notation("121212").unwrap();
+        assert_eq!(game.play(0).to_string(), "win: red from (0, 3) to (0, 0)");
+        assert_eq!(format!("{:?}", PlayResult::Pass), "Pass");
+    }
+
+    #[test]
+    fn other_grid_sizes_are_played() {
+        let mut game = Game::default().grid_size(8, 7);
+        assert_eq!(game.perft(1), 8);
+
+        game.play_notation("8888888").unwrap();
+        assert!(game.col_full(7) && !game.legal_moves().contains(7));
+        assert!(game.play_notation("9").is_err());
+
+        let shown = game.to_string();
+        assert!(shown.starts_with(". . . . . . . r\n") && shown.ends_with("1 2 3 4 5 6 7 8\nyellow to play"), "{shown}");
+
+        let mut game = Game::default().grid_size(8, 7);
+        game.play_notation("516171").unwrap();
+        assert!(matches!(game.play(7), PlayResult::Win(lines) if lines[0].end() == (7, 0)));
+        assert_eq!(game.mirror_move(7), 0);
+        assert_ne!(game, Game::default());
+    }
+
+    #[test]
+    fn pops_need_the_variant_and_an_own_coin() {
+        let mut game = Game::default();
+        game.play_notation("12").unwrap();
+        assert!(matches!(game.play(POP), PlayResult::Error));
+
+        let mut game = Game::default().variant(&POP_OUT);
+        game.play_notation("12").unwrap();
+        assert!(game.legal_moves().contains(POP) && !game.legal_moves().contains(1 | POP));
+        assert!(game.play_notation("p2").is_err());
+
+        let before = game.grid().to_vec();
+        game.play_notation("p1").unwrap();
+        assert_eq!(game.col_height(0), 0);
+        assert!(game.player_turn() == Player::Yellow);
+
+        assert!(game.undo(POP));
+        assert!(game.grid() == before);
+        assert!(game.player_turn() == Player::Red);
+    }
+
+    #[test]
+    fn a_pop_can_complete_the_opponent_line() {
+        let mut game = Game::default().variant(&POP_OUT);
+        game.play_notation("11727364").unwrap();
+
+        assert!(matches!(game.play(POP), PlayResult::Lose(lines) if lines.len() == 1));
+    }
+
+    #[test]
+    fn a_full_grid_is_a_draw_only_without_pops() {
+        // pairs of columns alternate colours, no line anywhere
+        let mut grid = [None; Game::ROW * Game::COL];
+        for (index, cell) in grid.iter_mut().enumerate() {
+            let (col, row) = (index / Game::ROW, index % Game::ROW);
+            *cell = Some(if (col / 2 + row) % 2 == 0 { Player::Red } else { Player::Yellow });
         }
+
+        let game = Game::from_grid(grid, Player::Red).unwrap();
+        assert!(game.stalemate());
+
+        let game = game.variant(&POP_OUT);
+        assert!(!game.stalemate());
+        assert_eq!(game.legal_moves(), [POP, 1 | POP, 4 | POP, 5 | POP]);
     }
 }
@@ -1,65 +1,502 @@
-use super::{Board, Message as BoardMessage};
+use super::{Board, Clock, Game, Message as BoardMessage, Player, Score};
+use crate::animator::{Easing, Timeline};
+use crate::behaviour::{Behaviour, Human};
+use crate::cli::Options;
+use crate::net::{self, Broadcast, Session, Spectator, Update};
 
 use iced::{
     Application, Command, Subscription,
-    widget::canvas::Canvas,
-    time, Element, Length,
+    widget::{canvas::Canvas, button, column, container, row, text, text_input},
+    time, Alignment, Element, Length, Point,
 };
 
-pub enum Menu {
-    Start,
-    Game(Board)
+use std::thread::JoinHandle;
+use std::time::{Duration, Instant};
+
+/// Time between two ticks of the animations.
+const TICK: Duration = Duration::from_millis(16);
+/// Length of the slide of a new screen into place.
+const TRANSITION: f32 = 0.35;
+/// Pixels a new screen slides up from.
+const SLIDE: f32 = 60.0;
+
+pub struct Menu {
+    theme: iced::Theme,
+    first: Player,
+    best_of: Option<u16>,
+    broadcast: Option<Broadcast>,
+    screen: Screen,
+    /// Progress of the slide of the current screen, from 0 to 1 in x.
+    transition: Timeline,
+    /// Time of the last tick.
+    now: Instant,
+}
+
+/// Screens the menu slides between.
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Page {
+    Lobby, Game, Summary, Spectate,
+}
+
+enum Screen {
+    Lobby(Lobby),
+    Game(Box<Board>),
+    Spectate(Box<Spectate>),
+}
+
+/// Host or join a game over the network.
+struct Lobby {
+    address: String,
+    status: String,
+    pending: Option<JoinHandle<Result<Session, String>>>,
+}
+
+/// Follow a broadcast game, the board being played by the broadcast moves.
+struct Spectate {
+    spectator: Option<Spectator>,
+    board: Option<Board>,
+    eval: Option<Vec<f32>>,
+    status: String,
+}
+
+#[derive(Debug, Clone)]
+pub enum Message {
+    Board(BoardMessage),
+    Address(String),
+    Host,
+    Join,
+    Poll,
 }
 
 impl Application for Menu {
     type Executor = iced::executor::Default;
-    type Message = BoardMessage;
+    type Message = Message;
     type Theme = iced::Theme;
-    type Flags = ();
+    type Flags = Options;
+
+    fn new(options: Options) -> (Self, Command<Message>) {
+        let (broadcast, broadcast_error) = match options.broadcast.as_deref().map(Broadcast::listen) {
+            Some(Ok(broadcast)) => (Some(broadcast), None),
+            Some(Err(error)) => (None, Some(format!("Broadcast: {error}"))),
+            None => (None, None),
+        };
+
+        let mut menu = Menu {
+            theme: options.theme.clone(),
+            first: options.first,
+            best_of: options.best_of,
+            broadcast,
+            screen: Screen::Lobby(Lobby {
+                address: String::from(net::DEFAULT_ADDRESS),
+                status: broadcast_error.clone().unwrap_or_default(),
+                pending: None,
+            }),
+            transition: Timeline::new(Point::ORIGIN).then(Point::new(1.0, 0.0), TRANSITION, Easing::EaseOut),
+            now: Instant::now(),
+        };
+
+        menu.transition.restart();
+
+        if let Some(address) = &options.spectate {
+            let (spectator, status) = match Spectator::connect(address) {
+                Ok(spectator) => (Some(spectator), format!("Waiting for {address}")),
+                Err(error) => (None, error),
+            };
 
-    fn new(_flags: ()) -> (Self, Command<BoardMessage>) {
-        let p1 = Box::new(crate::behaviour::Human);
-        let p2 = Box::new(crate::behaviour::Rollout::default());
+            let spectate = Spectate { spectator, board: None, eval: None, status };
+            menu.screen = Screen::Spectate(Box::new(spectate));
+        }
+
+        if options.lan || options.spectate.is_some() {
+            return (menu, Command::none());
+        }
+
+        let seats = options.players.iter()
+            .map(|&player| (player, options.seat(player).behaviour()))
+            .collect();
+
+        let mut board = Board::new(seats).threats(options.threats);
+
+        if let Ok(game) = options.game() {
+            board = board.position(game);
+        }
+
+        if let Some(games) = options.best_of {
+            board = board.best_of(games);
+        }
+
+        if let Some(clock) = options.clock {
+            board = board.clock(clock);
+        }
+
+        if let Some(broadcast) = menu.broadcast.take() {
+            board = board.broadcast(broadcast);
+        }
+
+        if let Some(error) = broadcast_error {
+            board.report(error);
+        }
 
-        (Menu::Game(Board::new(p1, p2)), Command::none())
+        menu.screen = Screen::Game(Box::new(board));
+        (menu, Command::none())
     }
 
     fn title(&self) -> String {
         String::from("Connect four")
     }
 
-    fn update(&mut self, message: BoardMessage) -> Command<BoardMessage> {
-        if let Menu::Game(board) = self {
-            board.handle_message(message)
-        } else {
-            Command::none()
+    fn theme(&self) -> iced::Theme {
+        self.theme.clone()
+    }
+
+    fn update(&mut self, message: Message) -> Command<Message> {
+        if let Message::Board(BoardMessage::Tick(now)) = message {
+            self.now = now;
+        }
+
+        let page = self.page();
+        let command = self.handle(message);
+
+        if self.page() != page {
+            self.now = Instant::now();
+            self.transition.restart_at(self.now);
+        }
+
+        command
+    }
+
+    fn subscription(&self) -> Subscription<Message> {
+        let screen = self.screen_subscription();
+
+        match self.transition.finished_at(Instant::now()) {
+            true => screen,
+            false => Subscription::batch([screen, ticks()]),
+        }
+    }
+
+    fn view(&self) -> Element<'_, Self::Message, iced::Renderer<Self::Theme>> {
+        let content = match &self.screen {
+            Screen::Lobby(lobby) => lobby_view(lobby),
+            Screen::Game(board) if board.match_over() => summary(board.score(), board.players()),
+            Screen::Game(board) => board_view(board),
+            Screen::Spectate(spectate) => spectate.view(),
+        };
+
+        let progress = self.transition.point_at(self.now).x;
+        let slide = ((1.0 - progress) * SLIDE).round() as u16;
+
+        container(content)
+            .width(Length::Fill)
+            .height(Length::Fill)
+            .padding([slide, 0, 0, 0])
+            .into()
+    }
+}
+
+impl Menu {
+    fn page(&self) -> Page {
+        match &self.screen {
+            Screen::Lobby(_) => Page::Lobby,
+            Screen::Game(board) if board.match_over() => Page::Summary,
+            Screen::Game(_) => Page::Game,
+            Screen::Spectate(_) => Page::Spectate,
         }
     }
 
-    fn subscription(&self) -> Subscription<BoardMessage> {
-        use std::time::Duration;
+    fn handle(&mut self, message: Message) -> Command<Message> {
+        let lobby = match (&mut self.screen, message) {
+            (Screen::Game(board), Message::Board(message)) => {
+                return board.handle_message(message).map(Message::Board);
+            }
+            (Screen::Spectate(spectate), message) => return spectate.update(message),
+            (Screen::Lobby(lobby), message) => (lobby, message),
+            _ => return Command::none(),
+        };
+
+        match lobby {
+            (lobby, Message::Address(address)) => lobby.address = address,
+            (lobby, Message::Host) if lobby.pending.is_none() => match net::Host::bind(&lobby.address) {
+                Ok(host) => {
+                    let first = self.first;
+
+                    lobby.status = format!("Waiting for a guest on {}", lobby.address);
+                    lobby.pending = Some(std::thread::spawn(move || host.accept(first, Player::Red)));
+                }
+                Err(error) => lobby.status = error,
+            },
+            (lobby, Message::Join) if lobby.pending.is_none() => {
+                let address = lobby.address.clone();
+
+                lobby.status = format!("Joining {address}");
+                lobby.pending = Some(std::thread::spawn(move || net::join(&address)));
+            }
+            (lobby, Message::Poll) if lobby.pending.as_ref().is_some_and(JoinHandle::is_finished) => {
+                let result = lobby.pending.take()
+                    .and_then(|handle| handle.join().ok())
+                    .unwrap_or_else(|| Err(String::from("the connection crashed")));
 
-        match self {
-            Menu::Start => todo!(),
-            Menu::Game(board) => {
-                if !board.animation_finished() {
-                    time::every(Duration::from_millis(16)).map(BoardMessage::Tick)
-                } else {
-                    Subscription::none()
+                match result {
+                    Ok(session) => {
+                        let board = lan_board(session, self.best_of, self.broadcast.take());
+                        self.screen = Screen::Game(Box::new(board));
+                    }
+                    Err(error) => lobby.status = error,
                 }
             }
+            _ => (),
         }
+
+        Command::none()
     }
 
-    fn view(&self) -> Element<'_, Self::Message, iced::Renderer<Self::Theme>> {
-        match self {
-            Menu::Start => todo!(),
-            Menu::Game(board) => {
-                Canvas::new(board)
-                    .width(Length::Fill)
-                    .height(Length::Fill)
-                    .into()
+    fn screen_subscription(&self) -> Subscription<Message> {
+        match &self.screen {
+            Screen::Lobby(lobby) if lobby.pending.is_some() => {
+                time::every(Duration::from_millis(100)).map(|_| Message::Poll)
+            }
+            Screen::Lobby(_) => Subscription::none(),
+            Screen::Game(board) => board_ticks(board),
+            Screen::Spectate(spectate) => {
+                let poll = time::every(Duration::from_millis(100)).map(|_| Message::Poll);
+
+                match &spectate.board {
+                    Some(board) if spectate.spectator.is_some() => Subscription::batch([poll, board_ticks(board)]),
+                    Some(board) => board_ticks(board),
+                    None => poll,
+                }
+            }
+        }
+    }
+}
+
+impl Spectate {
+    fn update(&mut self, message: Message) -> Command<Message> {
+        match message {
+            // spectators only watch, the clicks and keys besides the
+            // threats are ignored
+            Message::Board(message @ (BoardMessage::Tick(_) | BoardMessage::Play(_) | BoardMessage::Threats)) => {
+                if let Some(board) = &mut self.board {
+                    return board.handle_message(message).map(Message::Board);
+                }
+            }
+            Message::Poll => self.poll(),
+            _ => (),
+        }
+
+        Command::none()
+    }
+
+    fn poll(&mut self) {
+        let Some(spectator) = &mut self.spectator else {
+            return;
+        };
+
+        while let Some(update) = spectator.poll() {
+            match update {
+                Update::Start { position, score, red, yellow, .. } => {
+                    let mut board = Board::new(vec![(Player::Red, Box::new(red)), (Player::Yellow, Box::new(yellow))])
+                        .position(*position)
+                        .standings(score);
+
+                    if let Some(clock) = self.board.as_ref().and_then(Board::clock_state) {
+                        board.sync_clock(clock.remaining(Player::Red), clock.remaining(Player::Yellow));
+                    }
+
+                    self.board = Some(board);
+                    self.eval = None;
+                    self.status.clear();
+                }
+                Update::Clock { red, yellow } => {
+                    if let Some(board) = &mut self.board {
+                        board.sync_clock(red, yellow);
+                    }
+                }
+                Update::Eval(eval) => self.eval = Some(eval),
+                Update::Error(error) => match &mut self.board {
+                    Some(board) => board.report(format!("Spectator: {error}")),
+                    None => self.status = format!("Spectator: {error}"),
+                },
+                Update::Lost => {
+                    self.spectator = None;
+                    self.status = String::from("The broadcast ended");
+                    return;
+                }
+            }
+        }
+    }
+
+    fn view(&self) -> Element<'_, Message, iced::Renderer> {
+        let Some(board) = &self.board else {
+            return container(text(&self.status).size(32))
+                .width(Length::Fill)
+                .height(Length::Fill)
+                .center_x()
+                .center_y()
+                .into();
+        };
+
+        let status = match &self.eval {
+            _ if !self.status.is_empty() => self.status.clone(),
+            Some(eval) => {
+                let (col, score) = eval.iter()
+                    .enumerate()
+                    .fold((0, f32::MIN), |best, (col, &score)| if score > best.1 { (col, score) } else { best });
+
+                format!("Best move {} ({score:+.2})", col + 1)
             }
+            None => String::from("Evaluating"),
+        };
+
+        let status = container(text(status).size(20)).width(Length::Fill).center_x();
+        column![board_view(board), status].into()
+    }
+}
+
+fn board_ticks(board: &Board) -> Subscription<Message> {
+    if !board.animation_finished() || board.clock_running() {
+        ticks()
+    } else {
+        Subscription::none()
+    }
+}
+
+/// Ticks driving the animations of the board and of the menu.
+fn ticks() -> Subscription<Message> {
+    time::every(TICK).map(|now| Message::Board(BoardMessage::Tick(now)))
+}
+
+fn board_view(board: &Board) -> Element<'_, Message, iced::Renderer> {
+    let canvas: Element<'_, BoardMessage, iced::Renderer> = Canvas::new(board)
+        .width(Length::Fill)
+        .height(Length::Fill)
+        .into();
+
+    let mut content = column![header(board.score(), board.clock_state(), board.players())];
+
+    if let Some(status) = board.status() {
+        content = content.push(container(text(status).size(18)).width(Length::Fill).center_x());
+    }
+
+    content.push(canvas.map(Message::Board)).into()
+}
+
+/// Board of a connected game, the local seat is played by hand.
+fn lan_board(session: Session, best_of: Option<u16>, broadcast: Option<Broadcast>) -> Board {
+    let local: Box<dyn Behaviour> = Box::new(Human);
+    let remote: Box<dyn Behaviour> = Box::new(session.remote);
+
+    let seats = vec![(session.local, local), (session.local.opponent(), remote)];
+    let mut board = Board::new(seats).position(Game::new(session.first));
+
+    if let Some(games) = best_of {
+        board = board.best_of(games);
+    }
+
+    match broadcast {
+        Some(broadcast) => board.broadcast(broadcast),
+        None => board,
+    }
+}
+
+fn lobby_view(lobby: &Lobby) -> Element<'_, Message, iced::Renderer> {
+    let mut host = button(text("Host")).padding(12);
+    let mut join = button(text("Join")).padding(12);
+
+    if lobby.pending.is_none() {
+        host = host.on_press(Message::Host);
+        join = join.on_press(Message::Join);
+    }
+
+    let content = column![
+        text("LAN game").size(48),
+        text_input("address:port", &lobby.address, Message::Address).padding(8).size(24),
+        row![host, join].spacing(24),
+        text(&lobby.status).size(20),
+    ]
+    .spacing(24)
+    .max_width(400)
+    .align_items(Alignment::Center);
+
+    container(content)
+        .width(Length::Fill)
+        .height(Length::Fill)
+        .center_x()
+        .center_y()
+        .into()
+}
+
+fn header<'a>(score: Score, clock: Option<Clock>, players: &[Player]) -> Element<'a, Message, iced::Renderer> {
+    let mut label = match players {
+        [first, second] => format!(
+            "{} {} - {} {}    Draws {}",
+            title(*first), score.wins(*first), score.wins(*second), title(*second), score.draws(),
+        ),
+        _ => {
+            let wins = players.iter().map(|&player| format!("{} {}", title(player), score.wins(player)));
+            format!("{}    Draws {}", wins.collect::<Vec<_>>().join("  "), score.draws())
         }
+    };
+
+    match score.match_length() {
+        Some(games) => label += &format!("    Game {}/{games}", (score.played() + 1).min(games)),
+        None => label += &format!("    Played {}", score.played()),
+    }
+
+    if let Some(clock) = clock {
+        let time = |player: Player| format_time(clock.remaining(player));
+
+        label = match players {
+            [first, second] => format!("{}  {label}  {}", time(*first), time(*second)),
+            _ => format!("{label}    {}", players.iter().map(|&player| time(player)).collect::<Vec<_>>().join(" ")),
+        };
+    }
+
+    container(text(label).size(24))
+        .width(Length::Fill)
+        .padding(8)
+        .center_x()
+        .into()
+}
+
+fn summary<'a>(score: Score, players: &[Player]) -> Element<'a, Message, iced::Renderer> {
+    let heading = match score.leader() {
+        Some(player) => format!("{} wins the match", title(player)),
+        None => String::from("The match is a draw"),
+    };
+
+    let wins = players.iter().map(|&player| score.wins(player).to_string());
+    let result = wins.collect::<Vec<_>>().join(" - ");
+    let details = format!("{} games played, {} draws", score.played(), score.draws());
+
+    let content = column![
+        text(heading).size(48),
+        text(result).size(64),
+        text(details).size(24),
+        button(text("New match")).padding(12).on_press(Message::Board(BoardMessage::Restart)),
+    ]
+    .spacing(24)
+    .align_items(Alignment::Center);
+
+    container(content)
+        .width(Length::Fill)
+        .height(Length::Fill)
+        .center_x()
+        .center_y()
+        .into()
+}
+
+/// Capitalized name of `player`.
+fn title(player: Player) -> String {
+    let name = player.name();
+    name[..1].to_uppercase() + &name[1..]
+}
+
+fn format_time(time: std::time::Duration) -> String {
+    let secs = time.as_secs();
+
+    if secs < 10 {
+        format!("{}.{}", secs, time.subsec_millis() / 100)
+    } else {
+        format!("{}:{:02}", secs / 60, secs % 60)
     }
 }
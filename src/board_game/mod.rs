@@ -1,10 +1,20 @@
 mod board;
+mod clock;
 mod game;
 mod menu;
+mod moves;
+pub mod rules;
+mod score;
 
-pub use game::PlayResult;
+pub use game::{move_column, move_notation, parse_move, split_notation, PlayResult, ANVIL, BOMB, POP};
+pub use game::Line;
 pub use game::Player;
 pub use game::Game;
+pub use moves::Moves;
+
+pub use score::Score;
+pub use clock::Clock;
+pub use rules::Rules;
 
 pub use board::Message;
 pub use board::Board;
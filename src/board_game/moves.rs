@@ -0,0 +1,95 @@
+use super::game::{move_column, ANVIL, BOMB, POP};
+use super::Game;
+
+/// Flags of the moves, in the order their moves are listed.
+const FLAGS: [u8; 4] = [0, POP, ANVIL, BOMB];
+
+/// A set of move codes kept as one bit per column and flag, listed plain
+/// drops first, then each special move, column after column.
+#[derive(Clone, Copy, Default, PartialEq, Eq, Hash, Debug)]
+pub struct Moves(u64);
+
+impl Moves {
+    /// Bit of `code`, `None` for codes no variant plays.
+    fn bit(code: u8) -> Option<u64> {
+        let col = move_column(code);
+        let flag = FLAGS.iter().position(|&flag| flag == code & (POP | ANVIL | BOMB))?;
+
+        (col < Game::MAX_COL).then_some(1 << (flag * Game::MAX_COL + col))
+    }
+
+    pub fn insert(&mut self, code: u8) {
+        self.0 |= Self::bit(code).expect("no such move");
+    }
+
+    pub fn remove(&mut self, code: u8) {
+        self.0 &= !Self::bit(code).unwrap_or(0);
+    }
+
+    pub fn contains(&self, code: u8) -> bool {
+        Self::bit(code).is_some_and(|bit| self.0 & bit != 0)
+    }
+
+    pub fn len(&self) -> usize {
+        self.0.count_ones() as usize
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0 == 0
+    }
+
+    /// Moves of both sets.
+    pub fn intersection(self, other: Self) -> Self {
+        Self(self.0 & other.0)
+    }
+}
+
+impl Iterator for Moves {
+    type Item = u8;
+
+    fn next(&mut self) -> Option<u8> {
+        let index = self.0.trailing_zeros() as usize;
+        if self.0 == 0 { return None; }
+
+        self.0 &= self.0 - 1;
+        Some((index % Game::MAX_COL) as u8 | FLAGS[index / Game::MAX_COL])
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.len(), Some(self.len()))
+    }
+}
+
+impl ExactSizeIterator for Moves {}
+
+impl<const N: usize> PartialEq<[u8; N]> for Moves {
+    fn eq(&self, codes: &[u8; N]) -> bool {
+        Iterator::eq(*self, codes.iter().copied())
+    }
+}
+
+impl FromIterator<u8> for Moves {
+    fn from_iter<I: IntoIterator<Item = u8>>(codes: I) -> Self {
+        let mut moves = Self::default();
+        codes.into_iter().for_each(|code| moves.insert(code));
+        moves
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn moves_list_drops_then_special_moves() {
+        let moves = [5 | BOMB, 6, POP, 3 | ANVIL, 2].into_iter().collect::<Moves>();
+
+        assert_eq!(moves.len(), 5);
+        assert!(moves.contains(POP) && !moves.contains(0) && !moves.contains(7) && !moves.contains(9));
+        assert_eq!(moves.collect::<Vec<_>>(), [2, 6, POP, 3 | ANVIL, 5 | BOMB]);
+
+        let mut moves = moves;
+        moves.remove(6);
+        assert_eq!(moves.intersection([2, 6].into_iter().collect()).collect::<Vec<_>>(), [2]);
+    }
+}
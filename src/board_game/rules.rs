@@ -0,0 +1,456 @@
+//! Variants of the game. A `Game` plays by one of the `Rules` below and the
+//! board, the terminal and the behaviours only go through `Game`, so a new
+//! variant is a new implementation of the trait added to `ALL`.
+
+use super::game::{move_column, ANVIL, BOMB, POP};
+use super::{Game, Moves, PlayResult, Player};
+
+pub trait Rules: Sync {
+    /// Word selecting the rules on the command line.
+    fn name(&self) -> &'static str;
+
+    /// Coins to align to win.
+    fn connect(&self) -> usize {
+        4
+    }
+
+    /// Pre-filled columns on each side of the grid, part of the lines but
+    /// never played.
+    fn side_columns(&self) -> usize {
+        0
+    }
+
+    /// Coin of the side column `col`, `-1` being the nearest on the left
+    /// and `game.cols()` the nearest on the right.
+    fn side_cell(&self, _col: i8, _row: usize) -> Option<Player> {
+        None
+    }
+
+    /// Mirroring a position left to right gives an equivalent one.
+    fn symmetric(&self) -> bool {
+        true
+    }
+
+    /// Flags of the moves besides a plain drop, with their names.
+    fn special_moves(&self) -> &'static [(u8, &'static str)] {
+        &[]
+    }
+
+    /// Variant state of a new game.
+    fn setup(&self) -> Tally {
+        Tally::default()
+    }
+
+    /// Every move the player to move may play.
+    fn legal_moves(&self, game: &Game) -> Moves;
+
+    fn play(&self, game: &mut Game, code: u8) -> PlayResult;
+
+    /// Take back `code`, the last move played. `false` when the rules
+    /// cannot, the position before has to be kept instead.
+    fn undo(&self, _game: &mut Game, _code: u8) -> bool {
+        false
+    }
+
+    /// Row where the coin of `code` comes to rest, `None` when the move
+    /// does not drop a coin.
+    fn landing(&self, game: &Game, code: u8) -> Option<usize> {
+        match code & POP {
+            0 => Some(game.col_height(move_column(code))),
+            _ => None,
+        }
+    }
+
+    /// Short text about the variant state, such as the captured coins.
+    fn status(&self, _game: &Game) -> Option<String> {
+        None
+    }
+}
+
+/// What a variant keeps besides the grid, set up again on restart.
+#[derive(Clone, Copy, Default, PartialEq, Eq, Hash, Debug)]
+pub struct Tally {
+    /// Coins captured by each player.
+    pub captured: [u8; 4],
+    /// Special coins each player still holds, as move flags.
+    pub powers: [u8; 4],
+    /// Column the player to move took a coin from and has to drop it
+    /// back elsewhere.
+    pub pending: Option<u8>,
+    /// The grid is being filled before the game proper.
+    pub filling: bool,
+}
+
+pub static STANDARD: Standard = Standard;
+pub static POP_OUT: PopOut = PopOut;
+pub static POP_TEN: PopTen = PopTen;
+pub static FIVE_IN_A_ROW: FiveInARow = FiveInARow;
+pub static POWER_UP: PowerUp = PowerUp;
+
+pub static ALL: [&dyn Rules; 5] = [&STANDARD, &POP_OUT, &POP_TEN, &FIVE_IN_A_ROW, &POWER_UP];
+
+pub fn by_name(name: &str) -> Option<&'static dyn Rules> {
+    ALL.iter().copied().find(|rules| rules.name() == name)
+}
+
+fn drops(game: &Game) -> impl Iterator<Item = u8> + '_ {
+    (0..game.cols()).filter(|&col| !game.col_full(col)).map(|col| col as u8)
+}
+
+/// Columns whose bottom coin belongs to the player to move.
+fn pops(game: &Game) -> impl Iterator<Item = u8> + '_ {
+    (0..game.cols())
+        .filter(|&col| game.cell(col as i8, 0) == Some(game.player_turn()))
+        .map(|col| col as u8 | POP)
+}
+
+/// Drop a coin in each column in turn, the first to align four wins.
+pub struct Standard;
+
+impl Rules for Standard {
+    fn name(&self) -> &'static str {
+        "standard"
+    }
+
+    fn legal_moves(&self, game: &Game) -> Moves {
+        drops(game).collect()
+    }
+
+    fn play(&self, game: &mut Game, code: u8) -> PlayResult {
+        match code {
+            col if (col as usize) < game.cols() => game.drop_coin(col as usize),
+            _ => PlayResult::Error,
+        }
+    }
+
+    fn undo(&self, game: &mut Game, code: u8) -> bool {
+        (code as usize) < game.cols() && game.take_back(code as usize)
+    }
+}
+
+/// Instead of dropping a coin, a player may take one of their own coins
+/// out of the bottom row, the column falling by one cell.
+pub struct PopOut;
+
+impl Rules for PopOut {
+    fn name(&self) -> &'static str {
+        "popout"
+    }
+
+    fn special_moves(&self) -> &'static [(u8, &'static str)] {
+        &[(POP, "pop")]
+    }
+
+    fn legal_moves(&self, game: &Game) -> Moves {
+        drops(game).chain(pops(game)).collect()
+    }
+
+    fn play(&self, game: &mut Game, code: u8) -> PlayResult {
+        if !self.legal_moves(game).contains(code) {
+            return PlayResult::Error;
+        }
+
+        match code & POP {
+            0 => game.drop_coin(code as usize),
+            _ => game.pop_coin(move_column(code)),
+        }
+    }
+
+    fn undo(&self, game: &mut Game, code: u8) -> bool {
+        match code & POP {
+            0 => STANDARD.undo(game, code),
+            _ => game.unpop(move_column(code)),
+        }
+    }
+}
+
+/// The grid is first filled row by row. Players then take one of their
+/// coins out of the bottom row: a coin that was part of four is captured
+/// and the player goes on, any other is dropped back in another column.
+/// Ten captured coins win, a player without a coin to take ends the game
+/// in a draw.
+pub struct PopTen;
+
+impl PopTen {
+    const CAPTURES: u8 = 10;
+}
+
+impl Rules for PopTen {
+    fn name(&self) -> &'static str {
+        "pop10"
+    }
+
+    fn special_moves(&self) -> &'static [(u8, &'static str)] {
+        &[(POP, "pop")]
+    }
+
+    fn setup(&self) -> Tally {
+        Tally { filling: true, ..Tally::default() }
+    }
+
+    fn legal_moves(&self, game: &Game) -> Moves {
+        let tally = game.tally();
+
+        if tally.filling {
+            let lowest = (0..game.cols()).map(|col| game.col_height(col)).min();
+            return drops(game).filter(|&col| Some(game.col_height(col as usize)) == lowest).collect();
+        }
+
+        match tally.pending {
+            Some(from) => {
+                let others = drops(game).filter(|&col| col != from).collect::<Moves>();
+                if others.is_empty() { [from].into_iter().collect() } else { others }
+            }
+            None => pops(game).collect(),
+        }
+    }
+
+    fn play(&self, game: &mut Game, code: u8) -> PlayResult {
+        if !self.legal_moves(game).contains(code) {
+            return PlayResult::Error;
+        }
+
+        let col = move_column(code);
+        let player = game.player_turn();
+
+        if game.tally().filling {
+            game.place(col);
+            game.pass_turn();
+            game.tally_mut().filling = !game.grid_full();
+
+            return PlayResult::Pass;
+        }
+
+        if code & POP == 0 {
+            game.place(col);
+            game.pass_turn();
+            game.tally_mut().pending = None;
+
+            return PlayResult::Pass;
+        }
+
+        let captured = !game.lines(col as i8, 0, player).is_empty();
+        game.remove(col, 0);
+
+        if !captured {
+            game.tally_mut().pending = Some(col as u8);
+            return PlayResult::Pass;
+        }
+
+        let count = &mut game.tally_mut().captured[player.index()];
+        *count += 1;
+
+        if *count == Self::CAPTURES { PlayResult::Win(Vec::new()) } else { PlayResult::Pass }
+    }
+
+    fn status(&self, game: &Game) -> Option<String> {
+        let captured = game.players().iter()
+            .map(|player| format!("{} {}", player.name(), game.tally().captured[player.index()]))
+            .collect::<Vec<_>>();
+
+        match game.tally().filling {
+            true => Some(String::from("filling the grid")),
+            false => Some(format!("captured: {} of {}", captured.join(", "), Self::CAPTURES)),
+        }
+    }
+}
+
+/// Five coins to align on a grid with an extra column on each side, both
+/// pre-filled with alternating colours.
+pub struct FiveInARow;
+
+impl Rules for FiveInARow {
+    fn name(&self) -> &'static str {
+        "five"
+    }
+
+    fn connect(&self) -> usize {
+        5
+    }
+
+    fn side_columns(&self) -> usize {
+        1
+    }
+
+    fn side_cell(&self, col: i8, row: usize) -> Option<Player> {
+        let red = row.is_multiple_of(2) == (col < 0);
+        Some(if red { Player::Red } else { Player::Yellow })
+    }
+
+    /// The side columns alternate in opposite orders.
+    fn symmetric(&self) -> bool {
+        false
+    }
+
+    fn legal_moves(&self, game: &Game) -> Moves {
+        STANDARD.legal_moves(game)
+    }
+
+    fn play(&self, game: &mut Game, code: u8) -> PlayResult {
+        STANDARD.play(game, code)
+    }
+
+    fn undo(&self, game: &mut Game, code: u8) -> bool {
+        STANDARD.undo(game, code)
+    }
+}
+
+/// Each player also holds an anvil, landing at the bottom of a column and
+/// crushing the coins below, and a bomb, blowing up the top coin of a
+/// column.
+pub struct PowerUp;
+
+impl Rules for PowerUp {
+    fn name(&self) -> &'static str {
+        "powerup"
+    }
+
+    fn special_moves(&self) -> &'static [(u8, &'static str)] {
+        &[(ANVIL, "anvil"), (BOMB, "bomb")]
+    }
+
+    fn setup(&self) -> Tally {
+        Tally { powers: [ANVIL | BOMB; 4], ..Tally::default() }
+    }
+
+    fn legal_moves(&self, game: &Game) -> Moves {
+        let powers = game.tally().powers[game.player_turn().index()];
+        let mut moves = drops(game).collect::<Moves>();
+
+        if powers & ANVIL != 0 {
+            (0..game.cols() as u8).for_each(|col| moves.insert(col | ANVIL));
+        }
+
+        if powers & BOMB != 0 {
+            (0..game.cols()).filter(|&col| game.col_height(col) > 0).for_each(|col| moves.insert(col as u8 | BOMB));
+        }
+
+        moves
+    }
+
+    fn play(&self, game: &mut Game, code: u8) -> PlayResult {
+        if !self.legal_moves(game).contains(code) {
+            return PlayResult::Error;
+        }
+
+        let col = move_column(code);
+        let player = game.player_turn();
+        game.tally_mut().powers[player.index()] &= !code;
+
+        if code & BOMB != 0 {
+            // taking the top coin away cannot complete a line
+            game.remove(col, game.col_height(col) - 1);
+            game.pass_turn();
+
+            return PlayResult::Pass;
+        }
+
+        if code & ANVIL != 0 {
+            while game.col_height(col) > 0 {
+                game.remove(col, 0);
+            }
+        }
+
+        game.drop_coin(col)
+    }
+
+    /// A bomb drops no coin, an anvil lands at the bottom.
+    fn landing(&self, game: &Game, code: u8) -> Option<usize> {
+        match code & (ANVIL | BOMB) {
+            0 => Some(game.col_height(move_column(code))),
+            ANVIL => Some(0),
+            _ => None,
+        }
+    }
+
+    fn status(&self, game: &Game) -> Option<String> {
+        let held = |player: Player| {
+            let powers = game.tally().powers[player.index()];
+            let names = self.special_moves().iter()
+                .filter(|&&(flag, _)| powers & flag != 0)
+                .map(|&(_, name)| name)
+                .collect::<Vec<_>>();
+
+            if names.is_empty() { String::from("none") } else { names.join(" ") }
+        };
+
+        let held = game.players().iter()
+            .map(|&player| format!("{}: {}", player.name(), held(player)))
+            .collect::<Vec<_>>();
+
+        Some(held.join(", "))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn played(rules: &'static dyn Rules, moves: &str) -> Game {
+        let mut game = Game::default().variant(rules);
+        game.play_notation(moves).unwrap();
+        game
+    }
+
+    #[test]
+    fn variants_are_found_by_name() {
+        for rules in ALL {
+            assert_eq!(by_name(rules.name()).map(Rules::name), Some(rules.name()));
+        }
+
+        assert!(by_name("chess").is_none());
+    }
+
+    #[test]
+    fn pop_ten_fills_row_by_row_then_captures() {
+        let mut game = played(&POP_TEN, "1234567");
+        assert_eq!(game.legal_moves(), [0, 1, 2, 3, 4, 5, 6]);
+
+        game.play_notation("1").unwrap();
+        assert_eq!(game.legal_moves(), [1, 2, 3, 4, 5, 6]);
+
+        // colours alternate along rows and columns, leaving no line
+        let mut game = played(&POP_TEN, &"1234567".repeat(Game::ROW));
+
+        assert!(game.grid_full() && !game.tally().filling);
+        assert!(game.legal_moves().all(|code| code & POP != 0));
+
+        // a coin that was not part of four goes back elsewhere
+        let pop = game.legal_moves().next().unwrap();
+        let player = game.player_turn();
+        assert!(matches!(game.play(pop), PlayResult::Pass));
+        assert!(game.player_turn() == player);
+        assert!(!game.legal_moves().contains(pop & !POP));
+    }
+
+    #[test]
+    fn five_in_a_row_counts_the_side_columns() {
+        // red 1 2 3 4 on the bottom row with the red side coin on the left
+        let mut game = played(&FIVE_IN_A_ROW, "172737");
+        assert!(game.cell(-1, 0) == Some(Player::Red));
+
+        assert!(matches!(game.play(3), PlayResult::Win(lines) if lines[0].start() == (-1, 0)));
+
+        // four away from the sides is not enough
+        let mut game = played(&FIVE_IN_A_ROW, "334455");
+        assert!(matches!(game.play(5), PlayResult::Pass));
+    }
+
+    #[test]
+    fn power_up_anvils_and_bombs_are_played_once() {
+        let mut game = played(&POWER_UP, "4444");
+        assert!(game.legal_moves().contains(3 | ANVIL));
+
+        assert_eq!(POWER_UP.landing(&game, 3 | ANVIL), Some(0));
+        assert_eq!(POWER_UP.landing(&game, 3 | BOMB), None);
+
+        game.play_notation("a4").unwrap();
+        assert_eq!(game.col_height(3), 1);
+        assert!(game.cell(3, 0) == Some(Player::Red));
+        assert!(game.tally().powers[..2] == [BOMB, ANVIL | BOMB]);
+
+        game.play_notation("b4").unwrap();
+        assert_eq!(game.col_height(3), 0);
+        assert!(game.tally().powers[..2] == [BOMB, ANVIL]);
+    }
+}
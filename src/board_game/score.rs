@@ -0,0 +1,154 @@
+use super::Player;
+
+#[derive(Clone, Copy, Default)]
+pub struct Score {
+    wins: [u16; 4],
+    draws: u16,
+    played: u16,
+
+    best_of: Option<u16>,
+}
+
+impl Score {
+    pub fn best_of(games: u16) -> Self {
+        Self { best_of: Some(games.max(1)), .. Default::default() }
+    }
+
+    pub fn record_win(&mut self, player: Player) {
+        self.wins[player.index()] += 1;
+        self.played += 1;
+    }
+
+    pub fn record_draw(&mut self) {
+        self.draws += 1;
+        self.played += 1;
+    }
+
+    /// `loser` gave up a game of `players`: every other player is credited
+    /// with the win, still counting a single game.
+    pub fn record_forfeit(&mut self, loser: Player, players: &[Player]) {
+        for &player in players.iter().filter(|&&player| player != loser) {
+            self.wins[player.index()] += 1;
+        }
+        self.played += 1;
+    }
+
+    pub fn wins(&self, player: Player) -> u16 {
+        self.wins[player.index()]
+    }
+
+    pub fn draws(&self) -> u16 {
+        self.draws
+    }
+
+    pub fn played(&self) -> u16 {
+        self.played
+    }
+
+    pub fn match_length(&self) -> Option<u16> {
+        self.best_of
+    }
+
+    /// A best of N match ends as soon as the leader can no longer be
+    /// caught up, or when every game of the series has been played.
+    pub fn match_over(&self) -> bool {
+        let Some(games) = self.best_of else {
+            return false;
+        };
+
+        let remaining = games.saturating_sub(self.played());
+        let [first, second, ..] = self.ranked();
+
+        remaining == 0 || first - second > remaining
+    }
+
+    /// Player with strictly more wins than every other, if any.
+    pub fn leader(&self) -> Option<Player> {
+        let [first, second, ..] = self.ranked();
+
+        match first > second {
+            true => Player::ALL.into_iter().find(|player| self.wins(*player) == first),
+            false => None,
+        }
+    }
+
+    pub fn reset(&mut self) {
+        *self = Self { best_of: self.best_of, .. Default::default() };
+    }
+
+    /// Win counts from the highest.
+    fn ranked(&self) -> [u16; 4] {
+        let mut wins = self.wins;
+        wins.sort_unstable_by(|a, b| b.cmp(a));
+        wins
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn draws_count_as_played_games() {
+        let mut score = Score::best_of(2);
+        score.record_draw();
+        assert!(!score.match_over());
+
+        score.record_draw();
+        assert_eq!((score.played(), score.draws()), (2, 2));
+        assert!(score.match_over() && score.leader().is_none());
+    }
+
+    #[test]
+    fn a_match_ends_once_the_leader_cannot_be_caught() {
+        let mut score = Score::best_of(5);
+        score.record_win(Player::Red);
+        score.record_win(Player::Red);
+        assert!(!score.match_over());
+
+        // 3 - 0 with 2 games left
+        score.record_win(Player::Red);
+        assert!(score.match_over());
+        assert_eq!(score.leader(), Some(Player::Red));
+
+        score.reset();
+        assert_eq!((score.played(), score.match_length()), (0, Some(5)));
+    }
+
+    #[test]
+    fn the_top_two_tied_have_no_leader() {
+        let mut score = Score::best_of(4);
+        score.record_win(Player::Red);
+        score.record_win(Player::Yellow);
+        score.record_win(Player::Green);
+        assert!(!score.match_over() && score.leader().is_none());
+
+        score.record_win(Player::Yellow);
+        assert!(score.match_over() && score.leader() == Some(Player::Yellow));
+
+        // a tie at the end of the series still ends the match
+        let mut score = Score::best_of(2);
+        score.record_win(Player::Red);
+        score.record_win(Player::Yellow);
+        assert!(score.match_over() && score.leader().is_none());
+
+        // without a series the match goes on
+        let mut score = Score::default();
+        score.record_win(Player::Red);
+        assert!(!score.match_over());
+    }
+
+    #[test]
+    fn a_forfeit_credits_every_other_player_once() {
+        let players = [Player::Red, Player::Yellow, Player::Green];
+        let mut score = Score::best_of(2);
+        score.record_forfeit(Player::Green, &players);
+
+        assert_eq!((score.wins(Player::Red), score.wins(Player::Yellow), score.wins(Player::Green)), (1, 1, 0));
+        assert_eq!((score.played(), score.draws()), (1, 0));
+        assert!(!score.match_over() && score.leader().is_none());
+
+        score.record_win(Player::Yellow);
+        assert!(score.match_over() && score.leader() == Some(Player::Yellow));
+    }
+}
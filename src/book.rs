@@ -0,0 +1,218 @@
+//! Opening books: the best move of every position of the first plies,
+//! found by a deep search.
+//!
+//! On disk a book is the magic `C4BK`, a version byte, the variant name
+//! preceded by its length, the plies and the search depth, the number of
+//! entries as a little endian `u32`, then the entries sorted by key and
+//! check: the key and the check as little endian `u64`s, the move code and
+//! the score as a little endian `i16`.
+
+use crate::board_game::{rules, Game, PlayResult, Rules};
+use crate::database;
+use crate::search::Search;
+
+use std::collections::HashSet;
+
+const MAGIC: &[u8; 4] = b"C4BK";
+const VERSION: u8 = 3;
+const ENTRY_SIZE: usize = 19;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Entry {
+    /// Key of the position seen by the player to move, as in a database.
+    pub key: u64,
+    /// Second hash of the position, telling apart positions whose keys
+    /// collide.
+    pub check: u64,
+    /// Best move in the canonical position.
+    pub code: u8,
+    /// Score of the move for the player to move, see `search::WIN`.
+    pub score: i16,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct OpeningBook {
+    variant: String,
+    plies: u8,
+    depth: u8,
+    entries: Vec<Entry>,
+}
+
+impl OpeningBook {
+    /// Search every position reached in less than `plies` moves of a two
+    /// player game of `rules`, `depth` moves ahead. Positions are keyed as
+    /// seen by the player to move, so that an entry serves whoever
+    /// started. `progress` is told the positions searched and the total.
+    pub fn generate(rules: &'static dyn Rules, plies: u8, depth: u8, mut progress: impl FnMut(usize, usize)) -> Self {
+        let positions = positions(Game::default().variant(rules), plies);
+
+        let mut search = Search::default();
+        let mut entries = Vec::with_capacity(positions.len());
+
+        for (index, game) in positions.iter().enumerate() {
+            let ((key, check), mirrored) = database::key(game).expect("two player positions");
+
+            if let Some((code, score)) = search.best_move(game, depth) {
+                let code = if mirrored { game.mirror_move(code) } else { code };
+                entries.push(Entry { key, check, code, score });
+            }
+
+            progress(index + 1, positions.len());
+        }
+
+        entries.sort_unstable_by_key(|entry| (entry.key, entry.check));
+        Self { variant: String::from(rules.name()), plies, depth, entries }
+    }
+
+    /// Best move of the player to move and its score, if the position is
+    /// in the book.
+    pub fn lookup(&self, game: &Game) -> Option<(u8, i16)> {
+        if game.rules().name() != self.variant {
+            return None;
+        }
+
+        let (key, mirrored) = database::key(game)?;
+        let index = self.entries.binary_search_by_key(&key, |entry| (entry.key, entry.check)).ok()?;
+        let Entry { code, score, .. } = self.entries[index];
+        let code = if mirrored { game.mirror_move(code) } else { code };
+
+        game.legal_moves().contains(code).then_some((code, score))
+    }
+
+    pub fn variant(&self) -> &str {
+        &self.variant
+    }
+
+    pub fn plies(&self) -> u8 {
+        self.plies
+    }
+
+    pub fn depth(&self) -> u8 {
+        self.depth
+    }
+
+    pub fn entries(&self) -> &[Entry] {
+        &self.entries
+    }
+
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = Vec::with_capacity(16 + self.variant.len() + self.entries.len() * ENTRY_SIZE);
+
+        bytes.extend_from_slice(MAGIC);
+        bytes.push(VERSION);
+        bytes.push(self.variant.len() as u8);
+        bytes.extend_from_slice(self.variant.as_bytes());
+        bytes.extend_from_slice(&[self.plies, self.depth]);
+        bytes.extend_from_slice(&(self.entries.len() as u32).to_le_bytes());
+
+        for entry in &self.entries {
+            bytes.extend_from_slice(&entry.key.to_le_bytes());
+            bytes.extend_from_slice(&entry.check.to_le_bytes());
+            bytes.push(entry.code);
+            bytes.extend_from_slice(&entry.score.to_le_bytes());
+        }
+
+        bytes
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, String> {
+        let invalid = || String::from("not an opening book");
+        let mut rest = bytes.strip_prefix(MAGIC).ok_or_else(invalid)?;
+
+        let mut take = |count: usize| {
+            let (taken, tail) = rest.split_at_checked(count).ok_or_else(invalid)?;
+            rest = tail;
+            Ok::<_, String>(taken)
+        };
+
+        let version = take(1)?[0];
+        if version != VERSION {
+            return Err(format!("unsupported opening book version {version}"));
+        }
+
+        let length = take(1)?[0] as usize;
+        let variant = String::from_utf8(take(length)?.to_vec()).map_err(|_| invalid())?;
+        if rules::by_name(&variant).is_none() {
+            return Err(format!("opening book of the unknown variant '{variant}'"));
+        }
+
+        let header = take(2)?;
+        let (plies, depth) = (header[0], header[1]);
+        let count = u32::from_le_bytes(take(4)?.try_into().map_err(|_| invalid())?) as usize;
+
+        let entries = take(count.checked_mul(ENTRY_SIZE).ok_or_else(invalid)?)?
+            .chunks_exact(ENTRY_SIZE)
+            .map(|chunk| Entry {
+                key: u64::from_le_bytes(chunk[..8].try_into().unwrap()),
+                check: u64::from_le_bytes(chunk[8..16].try_into().unwrap()),
+                code: chunk[16],
+                score: i16::from_le_bytes(chunk[17..].try_into().unwrap()),
+            })
+            .collect::<Vec<_>>();
+
+        if !rest.is_empty() || !entries.is_sorted_by_key(|entry| (entry.key, entry.check)) {
+            return Err(invalid());
+        }
+
+        Ok(Self { variant, plies, depth, entries })
+    }
+
+    pub fn load(path: &str) -> Result<Self, String> {
+        let bytes = std::fs::read(path).map_err(|error| format!("cannot read '{path}': {error}"))?;
+        Self::from_bytes(&bytes).map_err(|error| format!("{path}: {error}"))
+    }
+
+    pub fn save(&self, path: &str) -> Result<(), String> {
+        std::fs::write(path, self.to_bytes()).map_err(|error| format!("cannot write '{path}': {error}"))
+    }
+}
+
+/// Every position reached from `start` in less than `plies` moves
+/// without ending the game, ply after ply, each one kept once with its
+/// mirror.
+fn positions(start: Game, plies: u8) -> Vec<Game> {
+    let mut seen = HashSet::new();
+    let mut positions = Vec::new();
+    let mut layer = vec![start];
+
+    for _ in 0..plies {
+        layer.retain(|game| seen.insert(database::key(game).map(|((key, check), _)| (key, check))));
+        positions.extend_from_slice(&layer);
+
+        layer = layer.iter()
+            .flat_map(|game| game.legal_moves().filter_map(move |code| {
+                let mut child = *game;
+                matches!(child.play(code), PlayResult::Pass).then_some(child)
+            }))
+            .collect();
+    }
+
+    positions
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::board_game::Player;
+
+    #[test]
+    fn books_find_mirrored_positions_and_survive_the_disk() {
+        let book = OpeningBook::generate(&rules::STANDARD, 3, 4, |_, _| ());
+
+        // the empty grid, 4 first moves and their replies
+        assert_eq!(book.entries().len(), 1 + 4 + 25);
+
+        let mut left = Game::default();
+        left.play_notation("12").unwrap();
+        let mut right = Game::new(Player::Yellow);
+        right.play_notation("76").unwrap();
+
+        let (code, score) = book.lookup(&left).unwrap();
+        assert_eq!(book.lookup(&right), Some((left.mirror_move(code), score)));
+        assert_eq!(book.lookup(&Game::default().grid_size(8, 6)), None);
+        assert_eq!(book.lookup(&Game::default().variant(&rules::POP_OUT)), None);
+
+        assert_eq!(OpeningBook::from_bytes(&book.to_bytes()), Ok(book));
+        assert!(OpeningBook::from_bytes(b"C4BK").is_err());
+    }
+}
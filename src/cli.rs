@@ -0,0 +1,551 @@
+use crate::behaviour::{policy, Behaviour, Book, External, Human, Policy, Random, Rollout};
+use crate::board_game::{rules, Clock, Game, Player, Rules};
+use crate::book::OpeningBook;
+
+use std::sync::Arc;
+use std::time::Duration;
+
+pub const USAGE: &str = "\
+Usage: connect-four [OPTIONS]
+
+Options:
+  --red <SEAT>          behaviour of the red seat [default: human]
+  --yellow <SEAT>       behaviour of the yellow seat [default: rollout]
+  --green <SEAT>        behaviour of the green seat [default: rollout]
+  --blue <SEAT>         behaviour of the blue seat [default: rollout]
+  --players <PLAYERS>   2 to 4 of red, yellow, green and blue in turn order, e.g.
+                        red,green,yellow [default: red,yellow]
+  --first <PLAYER>      player starting the first game [default: the first player]
+  --variant <VARIANT>   standard, popout, pop10, five or powerup [default: standard]
+  --size <COLSxROWS>    grid size from 4x4 to 9x9 [default: 7x6]
+  --moves <MOVES>       initial position as 1-based columns, e.g. 4453, special moves
+                        take a letter: p4 pops, a4 drops an anvil, b4 a bomb
+  --best-of <GAMES>     play a match of N games
+  --clock <BASE+INC>    clocks in seconds, e.g. 120+2
+  --theme <THEME>       light or dark [default: light]
+  --window <PIXELS>     window width and height [default: 720]
+  --threats             mark the cells completing a line of each player, T
+                        shows or hides them
+  --headless            play the match in the terminal without opening a window
+  --tui                 play interactively in the terminal
+  --engine <SEAT>       serve SEAT over the line protocol on stdin/stdout
+  --client <ADDRESS>    play the --engine seat on the ladder server at ADDRESS
+  --name <NAME>         name given to the ladder server [default: the seat]
+  --lan                 host or join a game over the network, the host plays red
+  --broadcast <ADDRESS> let spectators follow the games, e.g. 0.0.0.0:4445
+  --spectate <ADDRESS>  follow the games broadcast at ADDRESS
+  -h, --help            print this help
+
+Seats:
+  human, random, rollout[:ITER[:DEEP[:POLICY]]], external:<COMMAND LINE>,
+  book:<FILE>[:<SEAT>] plays the opening book FILE then SEAT [default: rollout]
+
+Rollout policies:
+  random      playouts of random moves [default]
+  tactical    playouts taking wins, blocking losses and favouring the centre";
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Seat {
+    Human,
+    Random,
+    Rollout { iter: u16, deep: u16, policy: &'static dyn Policy },
+    External { program: String, args: Vec<String> },
+    Book { book: Arc<OpeningBook>, inner: Box<Seat> },
+}
+
+impl Seat {
+    pub fn behaviour(&self) -> Box<dyn Behaviour> {
+        match self {
+            Seat::Human => Box::new(Human),
+            Seat::Random => Box::<Random>::default(),
+            Seat::Rollout { iter, deep, policy } => Box::new(Rollout::new(*iter, *deep).policy(*policy)),
+            Seat::External { program, args } => Box::new(External::new(program, args.clone())),
+            Seat::Book { book, inner } => Box::new(Book::new(book.clone(), inner.behaviour())),
+        }
+    }
+
+    /// Single word naming the seat, such as `rollout` or the external program.
+    pub fn name(&self) -> String {
+        match self {
+            Seat::Human => String::from("human"),
+            Seat::Random => String::from("random"),
+            Seat::Rollout { policy, .. } if policy.name() == policy::RANDOM.name() => String::from("rollout"),
+            Seat::Rollout { policy, .. } => format!("{}-rollout", policy.name()),
+            Seat::External { program, .. } => {
+                let path = std::path::Path::new(program);
+                path.file_name().map_or(program.clone(), |name| name.to_string_lossy().replace(char::is_whitespace, "_"))
+            }
+            Seat::Book { inner, .. } => inner.name(),
+        }
+    }
+}
+
+impl std::str::FromStr for Seat {
+    type Err = String;
+
+    fn from_str(seat: &str) -> Result<Self, Self::Err> {
+        if let Some(command) = seat.strip_prefix("external:") {
+            let mut words = command.split_whitespace().map(String::from);
+            let program = words.next().ok_or_else(|| String::from("missing external program"))?;
+
+            return Ok(Seat::External { program, args: words.collect() });
+        }
+
+        if let Some(rest) = seat.strip_prefix("book:") {
+            let (path, inner) = rest.split_once(':').unwrap_or((rest, "rollout"));
+            let book = Arc::new(OpeningBook::load(path)?);
+
+            return Ok(Seat::Book { book, inner: Box::new(inner.parse()?) });
+        }
+
+        let mut parts = seat.split(':');
+
+        match parts.next() {
+            Some("human") => Ok(Seat::Human),
+            Some("random") => Ok(Seat::Random),
+            Some("rollout") => {
+                let mut param = |default| match parts.next() {
+                    Some(value) => value.parse().map_err(|_| format!("invalid rollout parameter '{value}'")),
+                    None => Ok(default),
+                };
+
+                let iter = param(250)?;
+                let deep = param(5)?;
+                let policy = match parts.next() {
+                    Some(name) => policy::by_name(name).ok_or_else(|| format!("unknown rollout policy '{name}'"))?,
+                    None => &policy::RANDOM,
+                };
+
+                Ok(Seat::Rollout { iter, deep, policy })
+            }
+            _ => Err(format!("unknown seat '{seat}'")),
+        }
+    }
+}
+
+pub struct Options {
+    pub red: Seat,
+    pub yellow: Seat,
+    pub green: Seat,
+    pub blue: Seat,
+    /// Seated players in turn order.
+    pub players: Vec<Player>,
+    pub first: Player,
+    pub rules: &'static dyn Rules,
+    /// Columns and rows of the grid.
+    pub size: (usize, usize),
+    pub moves: String,
+    pub best_of: Option<u16>,
+    pub clock: Option<Clock>,
+    pub theme: iced::Theme,
+    pub window_size: u32,
+    pub threats: bool,
+    pub headless: bool,
+    pub tui: bool,
+    pub engine: Option<Seat>,
+    pub client: Option<String>,
+    pub name: Option<String>,
+    pub lan: bool,
+    pub broadcast: Option<String>,
+    pub spectate: Option<String>,
+}
+
+impl Options {
+    /// Parse the arguments following the program name. `Ok(None)` means
+    /// the help was requested.
+    pub fn parse(mut args: impl Iterator<Item = String>) -> Result<Option<Self>, String> {
+        let mut options = Options::default();
+        let mut first = None;
+
+        while let Some(arg) = args.next() {
+            let mut value = || args.next().ok_or_else(|| format!("missing value for '{arg}'"));
+
+            match arg.as_str() {
+                "--red" => options.red = value()?.parse()?,
+                "--yellow" => options.yellow = value()?.parse()?,
+                "--green" => options.green = value()?.parse()?,
+                "--blue" => options.blue = value()?.parse()?,
+                "--players" => options.players = parse_players(&value()?)?,
+                "--first" => first = Some(parse_player(&value()?)?),
+                "--variant" => {
+                    let variant = value()?;
+                    options.rules = rules::by_name(&variant).ok_or_else(|| format!("unknown variant '{variant}'"))?;
+                }
+                "--moves" => options.moves = value()?,
+                "--size" => options.size = parse_size(&value()?)?,
+                "--best-of" => {
+                    let games = value()?;
+                    options.best_of = Some(games.parse().map_err(|_| format!("invalid game count '{games}'"))?);
+                }
+                "--clock" => options.clock = Some(parse_clock(&value()?)?),
+                "--theme" => {
+                    options.theme = match value()?.as_str() {
+                        "light" => iced::Theme::Light,
+                        "dark" => iced::Theme::Dark,
+                        theme => return Err(format!("unknown theme '{theme}'")),
+                    }
+                }
+                "--window" => {
+                    let size = value()?;
+                    options.window_size = size.parse().map_err(|_| format!("invalid window size '{size}'"))?;
+                }
+                "--threats" => options.threats = true,
+                "--headless" => options.headless = true,
+                "--tui" => options.tui = true,
+                "--engine" => options.engine = Some(value()?.parse()?),
+                "--client" => options.client = Some(value()?),
+                "--name" => {
+                    let name = value()?;
+                    if name.is_empty() || name.contains(char::is_whitespace) {
+                        return Err(format!("invalid name '{name}', a single word is expected"));
+                    }
+
+                    options.name = Some(name);
+                }
+                "--lan" => options.lan = true,
+                "--broadcast" => options.broadcast = Some(value()?),
+                "--spectate" => options.spectate = Some(value()?),
+                "-h" | "--help" => return Ok(None),
+                _ => return Err(format!("unknown argument '{arg}'")),
+            }
+        }
+
+        match (&options.client, &options.engine) {
+            (Some(_), None) => return Err(String::from("--client needs the --engine seat to play")),
+            (Some(_), Some(Seat::Human)) => return Err(String::from("--client plays a bot, the --engine seat cannot be human")),
+            _ => (),
+        }
+
+        options.first = first.unwrap_or(options.players[0]);
+        if !options.players.contains(&options.first) {
+            return Err(format!("the first player {} is not seated", options.first.name()));
+        }
+
+        let external = options.players.iter().any(|&player| matches!(options.seat(player), Seat::External { .. }));
+        let networked = options.lan || options.engine.is_some() || options.broadcast.is_some() || options.spectate.is_some();
+
+        if options.rules.name() != rules::STANDARD.name() && (networked || external) {
+            return Err(String::from("the network and the engine protocol only play the standard variant"));
+        }
+
+        let standard_size = options.size == (Game::COL, Game::ROW);
+        if !standard_size && (networked || external) {
+            return Err(format!("the network and the engine protocol only play on a {}x{} grid", Game::COL, Game::ROW));
+        }
+
+        for &player in &options.players {
+            if let Seat::Book { book, .. } = options.seat(player) {
+                if book.variant() != options.rules.name() {
+                    return Err(format!("the opening book of {} is for the {} variant", player.name(), book.variant()));
+                }
+
+                if !standard_size {
+                    return Err(format!("the opening book of {} is for the {}x{} grid", player.name(), Game::COL, Game::ROW));
+                }
+            }
+        }
+
+        let red_yellow = options.players.len() == 2 && options.players.iter().all(|player| [Player::Red, Player::Yellow].contains(player));
+        if !red_yellow && (networked || external) {
+            return Err(String::from("the network and the engine protocol only seat red and yellow"));
+        }
+
+        options.game()?;
+        Ok(Some(options))
+    }
+
+    /// Initial position described by the options.
+    pub fn game(&self) -> Result<Game, String> {
+        let (cols, rows) = self.size;
+        let mut game = Game::new(self.first).seating(&self.players).variant(self.rules).grid_size(cols, rows);
+
+        game.play_notation(&self.moves)?;
+
+        Ok(game)
+    }
+
+    /// Seat of `player`.
+    pub fn seat(&self, player: Player) -> &Seat {
+        match player {
+            Player::Red => &self.red,
+            Player::Yellow => &self.yellow,
+            Player::Green => &self.green,
+            Player::Blue => &self.blue,
+        }
+    }
+}
+
+impl Default for Options {
+    fn default() -> Self {
+        Self {
+            red: Seat::Human,
+            yellow: Seat::Rollout { iter: 250, deep: 5, policy: &policy::RANDOM },
+            green: Seat::Rollout { iter: 250, deep: 5, policy: &policy::RANDOM },
+            blue: Seat::Rollout { iter: 250, deep: 5, policy: &policy::RANDOM },
+            players: vec![Player::Red, Player::Yellow],
+            first: Player::Red,
+            rules: &rules::STANDARD,
+            size: (Game::COL, Game::ROW),
+            moves: String::new(),
+            best_of: None,
+            clock: None,
+            theme: iced::Theme::Light,
+            window_size: 720,
+            threats: false,
+            headless: false,
+            tui: false,
+            engine: None,
+            client: None,
+            name: None,
+            lan: false,
+            broadcast: None,
+            spectate: None,
+        }
+    }
+}
+
+/// Options of the `book` and `database` programs, searching every
+/// position of the first plies of a variant.
+pub struct Generator {
+    pub rules: &'static dyn Rules,
+    pub plies: u8,
+    pub depth: u8,
+    pub output: String,
+    /// File given to `--stats`, once `accept_stats` was called.
+    pub stats: Option<String>,
+    accepts_stats: bool,
+}
+
+impl Generator {
+    /// Defaults of a program writing to `output` the positions reached in
+    /// less than `plies` moves.
+    pub fn new(plies: u8, output: &str) -> Self {
+        Self { rules: &rules::STANDARD, plies, depth: 12, output: String::from(output), stats: None, accepts_stats: false }
+    }
+
+    /// Accept `--stats <FILE>`, reading a database instead of writing one.
+    pub fn accept_stats(mut self) -> Self {
+        self.accepts_stats = true;
+        self
+    }
+
+    /// Parse the arguments following the program name over the defaults.
+    /// `Ok(None)` means the help was requested.
+    pub fn parse(mut self, mut args: impl Iterator<Item = String>) -> Result<Option<Self>, String> {
+        while let Some(arg) = args.next() {
+            let mut value = || args.next().ok_or_else(|| format!("missing value for '{arg}'"));
+
+            match arg.as_str() {
+                "--variant" => {
+                    let variant = value()?;
+                    self.rules = rules::by_name(&variant).ok_or_else(|| format!("unknown variant '{variant}'"))?;
+                }
+                "--plies" => {
+                    let plies = value()?;
+                    self.plies = plies.parse().map_err(|_| format!("invalid ply count '{plies}'"))?;
+                }
+                "--depth" => {
+                    let depth = value()?;
+                    self.depth = depth.parse().map_err(|_| format!("invalid depth '{depth}'"))?;
+                }
+                "--output" => self.output = value()?,
+                "--stats" if self.accepts_stats => self.stats = Some(value()?),
+                "-h" | "--help" => return Ok(None),
+                _ => return Err(format!("unknown argument '{arg}'")),
+            }
+        }
+
+        Ok(Some(self))
+    }
+
+    /// Parse the arguments of the process, printing the help of `program`
+    /// and exiting when it was requested or on an invalid argument.
+    pub fn from_env(self, program: &str, about: &str) -> Self {
+        let usage = self.usage(program, about);
+
+        match self.parse(std::env::args().skip(1)) {
+            Ok(Some(generator)) => generator,
+            Ok(None) => {
+                println!("{usage}");
+                std::process::exit(0);
+            }
+            Err(error) => {
+                eprintln!("error: {error}\n\n{usage}");
+                std::process::exit(2);
+            }
+        }
+    }
+
+    /// Help of `program`, whose purpose is `about`, with the defaults.
+    pub fn usage(&self, program: &str, about: &str) -> String {
+        let stats = match self.accepts_stats {
+            true => "
+  --stats <FILE>        count the first player wins, losses and draws of each
+                        ply of the database FILE instead",
+            false => "",
+        };
+
+        format!("\
+Usage: {program} [OPTIONS]
+
+{about}
+
+Options:
+  --variant <VARIANT>   standard, popout, pop10, five or powerup [default: {}]
+  --plies <PLIES>       positions reached in less than PLIES moves [default: {}]
+  --depth <DEPTH>       moves searched ahead of each position, 42 solves them
+                        [default: {}]
+  --output <FILE>       file written [default: {}]{stats}
+  -h, --help            print this help",
+            self.rules.name(), self.plies, self.depth, self.output,
+        )
+    }
+
+    /// Show the positions searched so far on stderr.
+    pub fn progress(searched: usize, total: usize) {
+        eprint!("\rsearched {searched}/{total}");
+        let _ = std::io::Write::flush(&mut std::io::stderr());
+    }
+}
+
+fn parse_player(player: &str) -> Result<Player, String> {
+    Player::ALL.into_iter()
+        .find(|known| known.name() == player)
+        .ok_or_else(|| format!("unknown player '{player}'"))
+}
+
+/// Comma separated list of 2 to 4 distinct players.
+fn parse_players(players: &str) -> Result<Vec<Player>, String> {
+    let players = players.split(',').map(parse_player).collect::<Result<Vec<_>, _>>()?;
+
+    if !(2..=4).contains(&players.len()) {
+        return Err(String::from("2 to 4 players are seated"));
+    }
+
+    if players.iter().enumerate().any(|(index, player)| players[..index].contains(player)) {
+        return Err(String::from("a player is seated twice"));
+    }
+
+    Ok(players)
+}
+
+/// Grid size such as `8x7`, columns first.
+fn parse_size(size: &str) -> Result<(usize, usize), String> {
+    let side = |side: &str, max| side.parse().ok().filter(|side| (Game::MIN_SIDE..=max).contains(side));
+
+    size.split_once('x')
+        .and_then(|(cols, rows)| Some((side(cols, Game::MAX_COL)?, side(rows, Game::MAX_ROW)?)))
+        .ok_or_else(|| format!("invalid size '{size}', from {0}x{0} to {1}x{2}", Game::MIN_SIDE, Game::MAX_COL, Game::MAX_ROW))
+}
+
+pub fn parse_clock(clock: &str) -> Result<Clock, String> {
+    let (base, increment) = clock.split_once('+').unwrap_or((clock, "0"));
+    let seconds = |value: &str| {
+        value.parse::<f32>().ok()
+            .and_then(|seconds| Duration::try_from_secs_f32(seconds).ok())
+            .ok_or_else(|| format!("invalid clock '{clock}'"))
+    };
+
+    Ok(Clock::new(seconds(base)?, seconds(increment)?))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(args: &str) -> Result<Option<Options>, String> {
+        Options::parse(args.split_whitespace().map(String::from))
+    }
+
+    #[test]
+    fn seats_take_their_parameters() {
+        assert_eq!("human".parse(), Ok(Seat::Human));
+        assert_eq!("rollout".parse(), Ok(Seat::Rollout { iter: 250, deep: 5, policy: &policy::RANDOM }));
+        assert_eq!("rollout:100:3:tactical".parse(), Ok(Seat::Rollout { iter: 100, deep: 3, policy: &policy::TACTICAL }));
+        assert_eq!("external:engine --depth 4".parse(), Ok(Seat::External { program: String::from("engine"), args: vec![String::from("--depth"), String::from("4")] }));
+
+        assert_eq!("rollout:many".parse::<Seat>(), Err(String::from("invalid rollout parameter 'many'")));
+        assert_eq!("rollout:100:3:greedy".parse::<Seat>(), Err(String::from("unknown rollout policy 'greedy'")));
+        assert_eq!("external:".parse::<Seat>(), Err(String::from("missing external program")));
+        assert_eq!("robot".parse::<Seat>(), Err(String::from("unknown seat 'robot'")));
+    }
+
+    #[test]
+    fn options_are_parsed() {
+        let options = parse("--yellow random --players red,green,yellow --first green --best-of 3").unwrap().unwrap();
+        assert_eq!(options.yellow, Seat::Random);
+        assert_eq!(options.players, [Player::Red, Player::Green, Player::Yellow]);
+        assert_eq!(options.first, Player::Green);
+        assert_eq!(options.best_of, Some(3));
+        assert_eq!(options.size, (Game::COL, Game::ROW));
+
+        let options = parse("--size 8x7 --moves 8888888").unwrap().unwrap();
+        assert_eq!(options.size, (8, 7));
+        assert!(options.game().unwrap().col_full(7));
+
+        assert!(parse("--threats --help").unwrap().is_none());
+    }
+
+    #[test]
+    fn invalid_options_are_rejected() {
+        assert_eq!(parse("--red").err(), Some(String::from("missing value for '--red'")));
+        assert_eq!(parse("--colour red").err(), Some(String::from("unknown argument '--colour'")));
+        assert_eq!(parse("--players red,yellow,red").err(), Some(String::from("a player is seated twice")));
+        assert_eq!(parse("--players red").err(), Some(String::from("2 to 4 players are seated")));
+        assert_eq!(parse("--first green").err(), Some(String::from("the first player green is not seated")));
+        assert_eq!(parse("--client localhost:5000").err(), Some(String::from("--client needs the --engine seat to play")));
+        assert_eq!(parse("--client localhost:5000 --engine human").err(), Some(String::from("--client plays a bot, the --engine seat cannot be human")));
+        for size in ["8", "3x6", "7x10", "ax6"] {
+            assert_eq!(parse(&format!("--size {size}")).err(), Some(format!("invalid size '{size}', from 4x4 to 9x9")));
+        }
+
+        let variant = Some(String::from("the network and the engine protocol only play the standard variant"));
+        assert_eq!(parse("--variant popout --lan").err(), variant);
+        assert_eq!(parse("--variant five --yellow external:engine").err(), variant);
+
+        let size = Some(String::from("the network and the engine protocol only play on a 7x6 grid"));
+        assert_eq!(parse("--size 8x7 --lan").err(), size);
+        assert_eq!(parse("--size 6x6 --engine random").err(), size);
+
+        let seats = Some(String::from("the network and the engine protocol only seat red and yellow"));
+        assert_eq!(parse("--players red,green --engine random").err(), seats);
+        assert_eq!(parse("--players red,yellow,green --lan").err(), seats);
+    }
+
+    #[test]
+    fn clocks_have_a_base_and_an_increment() {
+        let mut clock = parse_clock("120+2.5").unwrap();
+        assert_eq!(clock.remaining(Player::Red), Duration::from_secs(120));
+        clock.press(Player::Red);
+        assert_eq!(clock.remaining(Player::Red), Duration::from_millis(122_500));
+
+        let mut clock = parse_clock("60").unwrap();
+        clock.press(Player::Yellow);
+        assert_eq!(clock.remaining(Player::Yellow), Duration::from_secs(60));
+
+        for invalid in ["-5+1", "5+x", "inf", "1e30", "NaN", "60+inf"] {
+            assert_eq!(parse_clock(invalid).err(), Some(format!("invalid clock '{invalid}'")));
+        }
+        assert_eq!(parse("--clock").err(), Some(String::from("missing value for '--clock'")));
+    }
+
+    #[test]
+    fn generators_share_their_options() {
+        let parse = |generator: Generator, args: &str| generator.parse(args.split_whitespace().map(String::from));
+
+        let book = parse(Generator::new(4, "book.bin"), "--variant popout --plies 6 --depth 20").unwrap().unwrap();
+        assert_eq!((book.rules.name(), book.plies, book.depth, book.output.as_str()), ("popout", 6, 20, "book.bin"));
+
+        assert!(parse(Generator::new(4, "book.bin"), "--help").unwrap().is_none());
+        assert_eq!(parse(Generator::new(4, "book.bin"), "--stats x.db").err(), Some(String::from("unknown argument '--stats'")));
+        assert_eq!(parse(Generator::new(4, "book.bin"), "--plies 300").err(), Some(String::from("invalid ply count '300'")));
+        assert_eq!(parse(Generator::new(4, "book.bin"), "--depth").err(), Some(String::from("missing value for '--depth'")));
+
+        let database = parse(Generator::new(6, "positions.db").accept_stats(), "--stats x.db").unwrap().unwrap();
+        assert_eq!(database.stats.as_deref(), Some("x.db"));
+
+        let usage = Generator::new(6, "positions.db").accept_stats().usage("database", "Solve the openings.");
+        assert!(usage.starts_with("Usage: database [OPTIONS]\n\nSolve the openings.\n"));
+        assert!(usage.contains("[default: positions.db]\n  --stats <FILE>"));
+    }
+}
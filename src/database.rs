@@ -0,0 +1,476 @@
+//! Position databases: every position reached in the first plies with
+//! its value, solved by search.
+//!
+//! A database file is a 32 byte header followed by 24 byte records sorted
+//! by key and check, so that it can be memory mapped or searched in place. The
+//! header holds the magic `C4DB`, a version byte, the plies, the search
+//! depth, a zero byte, the record count as a little endian `u64` and the
+//! variant name padded with zeros to 16 bytes. A record holds the key and
+//! the check as little endian `u64`s, the score as a little endian `i16`,
+//! the ply, the depth searched, the best move, whether the search was
+//! exhaustive and two zero bytes.
+
+use crate::board_game::{rules, Game, PlayResult, Rules};
+use crate::search::{Search, MATE, WIN};
+
+use std::collections::HashSet;
+use std::fs::File;
+use std::io::{Read, Seek, SeekFrom};
+use std::sync::Mutex;
+
+const MAGIC: &[u8; 4] = b"C4DB";
+const VERSION: u8 = 2;
+const HEADER_SIZE: usize = 32;
+const RECORD_SIZE: usize = 24;
+const NAME_SIZE: usize = 16;
+
+/// Best move of a record without any legal move.
+const NO_MOVE: u8 = u8::MAX;
+
+/// Value of a position for the player to move.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Value {
+    /// Won in that many moves of both players, the winning one included.
+    Win(u8),
+    /// Lost in that many moves of both players.
+    Loss(u8),
+    Draw,
+    /// Neither won nor lost within the depth searched.
+    Unknown,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Record {
+    /// Key of the position seen by the player to move, see `Database`.
+    pub key: u64,
+    /// Second hash of the position, telling apart positions whose keys
+    /// collide.
+    pub check: u64,
+    /// Score of the best move, see `search::WIN`.
+    pub score: i16,
+    /// Moves played to reach the position.
+    pub ply: u8,
+    pub depth: u8,
+    /// Best move in the canonical position.
+    pub code: Option<u8>,
+    /// Every move until the end of the game was searched.
+    pub exhaustive: bool,
+}
+
+impl Record {
+    pub fn value(&self) -> Value {
+        match self.score {
+            score if score > MATE => Value::Win((WIN - score + 1) as u8),
+            score if score < -MATE => Value::Loss((WIN + score + 1) as u8),
+            _ if self.exhaustive => Value::Draw,
+            _ => Value::Unknown,
+        }
+    }
+
+    fn to_bytes(self) -> [u8; RECORD_SIZE] {
+        let mut bytes = [0; RECORD_SIZE];
+
+        bytes[..8].copy_from_slice(&self.key.to_le_bytes());
+        bytes[8..16].copy_from_slice(&self.check.to_le_bytes());
+        bytes[16..18].copy_from_slice(&self.score.to_le_bytes());
+        bytes[18] = self.ply;
+        bytes[19] = self.depth;
+        bytes[20] = self.code.unwrap_or(NO_MOVE);
+        bytes[21] = self.exhaustive as u8;
+
+        bytes
+    }
+
+    fn from_bytes(bytes: &[u8]) -> Self {
+        Self {
+            key: u64::from_le_bytes(bytes[..8].try_into().unwrap()),
+            check: u64::from_le_bytes(bytes[8..16].try_into().unwrap()),
+            score: i16::from_le_bytes(bytes[16..18].try_into().unwrap()),
+            ply: bytes[18],
+            depth: bytes[19],
+            code: Some(bytes[20]).filter(|&code| code != NO_MOVE),
+            exhaustive: bytes[21] != 0,
+        }
+    }
+}
+
+/// What a database knows about a position.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Solved {
+    pub value: Value,
+    /// Best move of the player to move.
+    pub best: Option<u8>,
+    pub ply: u8,
+}
+
+enum Source {
+    Memory(Vec<u8>),
+    /// Records are read from the file as they are searched.
+    File(Mutex<File>),
+}
+
+/// Positions of two player games keyed as seen by the player to move, a
+/// position and its mirror sharing their key, so that a record serves
+/// whoever started.
+pub struct Database {
+    variant: String,
+    plies: u8,
+    depth: u8,
+    count: u64,
+    source: Source,
+}
+
+impl Database {
+    /// Search every position reached in less than `plies` moves of a two
+    /// player game of `rules`, `depth` moves ahead. `progress` is told the
+    /// positions searched and the total.
+    pub fn generate(rules: &'static dyn Rules, plies: u8, depth: u8, mut progress: impl FnMut(usize, usize)) -> Self {
+        let positions = positions(Game::default().variant(rules), plies);
+        let mut search = Search::default();
+        let mut records = Vec::with_capacity(positions.len());
+
+        for (index, &(ply, game)) in positions.iter().enumerate() {
+            let ((key, check), mirrored) = key(&game).expect("two player positions");
+            let best = search.best_move(&game, depth);
+            let empty = game.grid().iter().filter(|cell| cell.is_none()).count();
+
+            records.push(Record {
+                key, check, ply, depth,
+                score: best.map_or(0, |(_, score)| score),
+                code: best.map(|(code, _)| if mirrored { game.mirror_move(code) } else { code }),
+                exhaustive: rules.special_moves().is_empty() && depth as usize >= empty,
+            });
+
+            progress(index + 1, positions.len());
+        }
+
+        records.sort_unstable_by_key(|record| (record.key, record.check));
+
+        let mut bytes = header(rules.name(), plies, depth, records.len() as u64);
+        records.iter().for_each(|record| bytes.extend_from_slice(&record.to_bytes()));
+
+        Self {
+            variant: String::from(rules.name()),
+            plies, depth,
+            count: records.len() as u64,
+            source: Source::Memory(bytes),
+        }
+    }
+
+    /// Read a whole database in memory.
+    pub fn load(path: &str) -> Result<Self, String> {
+        let bytes = std::fs::read(path).map_err(|error| format!("cannot read '{path}': {error}"))?;
+        Self::from_bytes(bytes).map_err(|error| format!("{path}: {error}"))
+    }
+
+    /// Search a database in its file, without reading it in memory.
+    pub fn open(path: &str) -> Result<Self, String> {
+        let read = |error: std::io::Error| format!("cannot read '{path}': {error}");
+        let mut file = File::open(path).map_err(read)?;
+        let mut header = [0; HEADER_SIZE];
+
+        file.read_exact(&mut header).map_err(read)?;
+        let length = file.metadata().map_err(read)?.len();
+
+        let (variant, plies, depth, count) = parse_header(&header, length).map_err(|error| format!("{path}: {error}"))?;
+        Ok(Self { variant, plies, depth, count, source: Source::File(Mutex::new(file)) })
+    }
+
+    pub fn from_bytes(bytes: Vec<u8>) -> Result<Self, String> {
+        let header = bytes.get(..HEADER_SIZE).ok_or_else(|| String::from("not a position database"))?;
+        let (variant, plies, depth, count) = parse_header(header, bytes.len() as u64)?;
+
+        Ok(Self { variant, plies, depth, count, source: Source::Memory(bytes) })
+    }
+
+    /// Write a database read in memory or generated to `path`.
+    pub fn save(&self, path: &str) -> Result<(), String> {
+        match &self.source {
+            Source::Memory(bytes) => std::fs::write(path, bytes).map_err(|error| format!("cannot write '{path}': {error}")),
+            Source::File(_) => Err(String::from("the database is already in a file")),
+        }
+    }
+
+    /// What the database knows about `game`, `None` when the position is
+    /// not in it.
+    pub fn lookup(&self, game: &Game) -> Result<Option<Solved>, String> {
+        let Some((key, mirrored)) = key(game).filter(|_| game.rules().name() == self.variant) else {
+            return Ok(None);
+        };
+
+        let (mut low, mut high) = (0, self.count);
+
+        while low < high {
+            let middle = low + (high - low) / 2;
+            let record = self.record(middle)?;
+
+            match (record.key, record.check).cmp(&key) {
+                std::cmp::Ordering::Less => low = middle + 1,
+                std::cmp::Ordering::Greater => high = middle,
+                std::cmp::Ordering::Equal => {
+                    let best = record.code.map(|code| if mirrored { game.mirror_move(code) } else { code });
+
+                    return Ok(Some(Solved { value: record.value(), best, ply: record.ply }));
+                }
+            }
+        }
+
+        Ok(None)
+    }
+
+    /// Every record, sorted by key and check.
+    pub fn records(&self) -> impl Iterator<Item = Result<Record, String>> + '_ {
+        (0..self.count).map(|index| self.record(index))
+    }
+
+    pub fn variant(&self) -> &str {
+        &self.variant
+    }
+
+    pub fn plies(&self) -> u8 {
+        self.plies
+    }
+
+    pub fn depth(&self) -> u8 {
+        self.depth
+    }
+
+    pub fn len(&self) -> u64 {
+        self.count
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.count == 0
+    }
+
+    fn record(&self, index: u64) -> Result<Record, String> {
+        let offset = HEADER_SIZE + index as usize * RECORD_SIZE;
+
+        match &self.source {
+            Source::Memory(bytes) => Ok(Record::from_bytes(&bytes[offset..offset + RECORD_SIZE])),
+            Source::File(file) => {
+                let mut bytes = [0; RECORD_SIZE];
+                let mut file = file.lock().unwrap();
+
+                file.seek(SeekFrom::Start(offset as u64))
+                    .and_then(|_| file.read_exact(&mut bytes))
+                    .map_err(|error| format!("cannot read the database: {error}"))?;
+
+                Ok(Record::from_bytes(&bytes))
+            }
+        }
+    }
+}
+
+impl Game {
+    /// What `database` knows about the position, see `Database::lookup`.
+    pub fn solved(&self, database: &Database) -> Result<Option<Solved>, String> {
+        database.lookup(self)
+    }
+}
+
+/// Key and check of the position seen by the player to move, and whether
+/// its mirror gave them, shared with the opening books. `None` without a
+/// single opponent or off the standard grid.
+pub fn key(game: &Game) -> Option<((u64, u64), bool)> {
+    if !game.standard_size() {
+        return None;
+    }
+
+    let mover = game.player_turn();
+    let other = game.opponent_of(mover)?;
+    let tally = game.tally();
+
+    let bytes = |mirrored: bool| {
+        let cells = (0..Game::COL)
+            .map(move |col| if mirrored { game.mirror_column(col) } else { col })
+            .flat_map(move |col| game.grid()[col * Game::ROW..(col + 1) * Game::ROW].to_vec())
+            .map(move |cell| match cell {
+                None => 0,
+                Some(player) if player == mover => 1,
+                Some(_) => 2,
+            });
+
+        let pending = tally.pending.map(|code| if mirrored { game.mirror_move(code) } else { code });
+
+        cells.chain([
+            tally.captured[mover.index()], tally.captured[other.index()],
+            tally.powers[mover.index()], tally.powers[other.index()],
+            pending.unwrap_or(u8::MAX), tally.filling as u8,
+        ])
+    };
+
+    // FNV-1a
+    let hash = |bytes: &mut dyn Iterator<Item = u8>| {
+        bytes.fold(0xcbf2_9ce4_8422_2325, |hash: u64, byte| (hash ^ byte as u64).wrapping_mul(0x0100_0000_01b3))
+    };
+
+    // multiply and rotate, unrelated to FNV-1a
+    let check = |bytes: &mut dyn Iterator<Item = u8>| {
+        bytes.fold(0x9e37_79b9_7f4a_7c15, |hash: u64, byte| (hash.rotate_left(5) ^ byte as u64).wrapping_mul(0x517c_c1b7_2722_0a95))
+    };
+
+    let plain = (hash(&mut bytes(false)), check(&mut bytes(false)));
+    let mirror = (hash(&mut bytes(true)), check(&mut bytes(true)));
+
+    match game.rules().symmetric() && mirror < plain {
+        true => Some((mirror, true)),
+        false => Some((plain, false)),
+    }
+}
+
+/// Every position reached from `start` in less than `plies` moves without
+/// ending the game, with its ply, each one kept once: positions whose key
+/// and check are both equal are the same position or its mirror.
+fn positions(start: Game, plies: u8) -> Vec<(u8, Game)> {
+    let mut seen = HashSet::new();
+    let mut positions = Vec::new();
+    let mut layer = vec![start];
+
+    for ply in 0..plies {
+        layer.retain(|game| seen.insert(key(game).map(|((key, check), _)| (key, check))));
+        positions.extend(layer.iter().map(|&game| (ply, game)));
+
+        layer = layer.iter()
+            .flat_map(|game| game.legal_moves().filter_map(move |code| {
+                let mut child = *game;
+                matches!(child.play(code), PlayResult::Pass).then_some(child)
+            }))
+            .collect();
+    }
+
+    positions
+}
+
+fn header(variant: &str, plies: u8, depth: u8, count: u64) -> Vec<u8> {
+    let mut header = Vec::with_capacity(HEADER_SIZE);
+    let mut name = [0; NAME_SIZE];
+    name[..variant.len()].copy_from_slice(variant.as_bytes());
+
+    header.extend_from_slice(MAGIC);
+    header.extend_from_slice(&[VERSION, plies, depth, 0]);
+    header.extend_from_slice(&count.to_le_bytes());
+    header.extend_from_slice(&name);
+
+    header
+}
+
+/// Variant, plies, depth and record count of a header, checked against
+/// the length of the whole file.
+fn parse_header(header: &[u8], length: u64) -> Result<(String, u8, u8, u64), String> {
+    if !header.starts_with(MAGIC) {
+        return Err(String::from("not a position database"));
+    }
+
+    if header[4] != VERSION {
+        return Err(format!("unsupported position database version {}", header[4]));
+    }
+
+    let count = u64::from_le_bytes(header[8..16].try_into().unwrap());
+    let name = &header[16..HEADER_SIZE];
+    let variant = String::from_utf8_lossy(&name[..name.iter().position(|&byte| byte == 0).unwrap_or(NAME_SIZE)]).into_owned();
+
+    if rules::by_name(&variant).is_none() {
+        return Err(format!("position database of the unknown variant '{variant}'"));
+    }
+
+    if count.checked_mul(RECORD_SIZE as u64).and_then(|size| size.checked_add(HEADER_SIZE as u64)) != Some(length) {
+        return Err(String::from("the position database is truncated"));
+    }
+
+    Ok((variant, header[5], header[6], count))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::board_game::Player;
+
+    #[test]
+    fn positions_are_found_whoever_started_and_on_disk() {
+        let database = Database::generate(&rules::STANDARD, 3, 4, |_, _| ());
+        assert_eq!(database.len(), 1 + 4 + 25);
+
+        let mut red = Game::new(Player::Red);
+        red.play_notation("12").unwrap();
+        let mut yellow = Game::new(Player::Yellow);
+        yellow.play_notation("76").unwrap();
+
+        let solved = red.solved(&database).unwrap().unwrap();
+        assert_eq!(solved.ply, 2);
+        assert_eq!(yellow.solved(&database).unwrap().map(|solved| solved.best), Some(solved.best.map(|code| red.mirror_move(code))));
+
+        red.play_notation("3").unwrap();
+        assert_eq!(red.solved(&database), Ok(None));
+
+        let path = std::env::temp_dir().join(format!("connect-four-{}.db", std::process::id()));
+        let path = path.to_str().unwrap();
+        database.save(path).unwrap();
+
+        let opened = Database::open(path).unwrap();
+        assert_eq!(opened.lookup(&yellow), database.lookup(&yellow));
+        assert!(Database::load(path).unwrap().records().map(Result::unwrap).is_sorted_by_key(|record| record.key));
+
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn records_of_colliding_keys_are_told_apart() {
+        let game = Game::default();
+        let ((key, check), _) = key(&game).unwrap();
+
+        let database = |check| {
+            let record = Record { key, check, score: 0, ply: 0, depth: 4, code: Some(3), exhaustive: false };
+            let mut bytes = header(rules::STANDARD.name(), 1, 4, 1);
+            bytes.extend_from_slice(&record.to_bytes());
+            Database::from_bytes(bytes).unwrap()
+        };
+
+        assert_eq!(database(check).lookup(&game).unwrap().map(|solved| solved.best), Some(Some(3)));
+        assert_eq!(database(check ^ 1).lookup(&game), Ok(None));
+    }
+
+    #[test]
+    fn positions_are_kept_once_up_to_their_mirror() {
+        // the grid or its mirror, whichever is smaller
+        let canonical = |game: &Game| {
+            let cells = |mirrored: bool| (0..Game::COL)
+                .map(|col| if mirrored { game.mirror_column(col) } else { col })
+                .flat_map(|col| &game.grid()[col * Game::ROW..][..Game::ROW])
+                .map(|cell| cell.map(Player::index))
+                .collect::<Vec<_>>();
+
+            cells(false).min(cells(true))
+        };
+
+        let positions = positions(Game::default(), 5);
+        let mut kept = HashSet::new();
+        assert!(positions.iter().all(|(_, game)| kept.insert(canonical(game))));
+
+        // every game of less than 5 moves, none ends before the seventh
+        let mut reached = HashSet::new();
+        let mut layer = vec![Game::default()];
+
+        for _ in 0..5 {
+            reached.extend(layer.iter().map(canonical));
+            layer = layer.iter()
+                .flat_map(|game| game.legal_moves().map(move |code| {
+                    let mut child = *game;
+                    child.play(code);
+                    child
+                }))
+                .collect();
+        }
+
+        assert_eq!(kept, reached);
+    }
+
+    #[test]
+    fn values_count_the_moves_to_the_end() {
+        let record = Record { key: 0, check: 0, score: WIN - 2, ply: 0, depth: 4, code: Some(3), exhaustive: false };
+        assert_eq!(record.value(), Value::Win(3));
+        assert_eq!(Record { score: 1 - WIN, ..record }.value(), Value::Loss(2));
+        assert_eq!(Record { score: 0, ..record }.value(), Value::Unknown);
+        assert_eq!(Record { score: 0, exhaustive: true, ..record }.value(), Value::Draw);
+    }
+}
@@ -0,0 +1,203 @@
+use crate::behaviour::{Behaviour, Intent, RESIGN};
+use crate::board_game::{move_notation, Clock, Game, PlayResult, Player, Score};
+use crate::cli::{Options, Seat};
+use crate::net::Broadcast;
+
+use std::time::{Duration, Instant};
+
+/// Moves of a player over the match and the time they took.
+#[derive(Clone, Copy, Default)]
+struct Stats {
+    moves: u32,
+    thinking: Duration,
+}
+
+/// How a game of the match ended.
+enum Outcome {
+    Win(Player),
+    Draw,
+    /// The player gave up a game between more than two players.
+    Forfeit(Player),
+}
+
+impl Outcome {
+    /// `player` ran out of time, resigned or played an illegal move.
+    fn forfeit(game: &Game, player: Player) -> Self {
+        match game.opponent_of(player) {
+            Some(winner) => Outcome::Win(winner),
+            None => Outcome::Forfeit(player),
+        }
+    }
+}
+
+/// Play the match described by `options` in the terminal, one line per
+/// game, then compare the seats.
+pub fn run(options: &Options) -> Result<(), String> {
+    if options.players.iter().any(|&player| *options.seat(player) == Seat::Human) {
+        return Err(String::from("human seats need the graphical interface"));
+    }
+
+    let mut seats = options.players.iter()
+        .map(|&player| (player, options.seat(player).behaviour()))
+        .collect::<Vec<_>>();
+
+    let mut score = Score::best_of(options.best_of.unwrap_or(1));
+    let mut game = options.game()?;
+    let mut opening = options.moves.clone();
+
+    let broadcast = options.broadcast.as_deref().map(Broadcast::listen).transpose()?;
+    let mut stats = [Stats::default(); 4];
+
+    while !score.match_over() {
+        let mut clock = options.clock;
+        let mut moves = std::mem::take(&mut opening);
+
+        if let Some(broadcast) = &broadcast {
+            broadcast.new_game(game, score, clock);
+        }
+
+        let outcome = loop {
+            let player = game.player_turn();
+            let behaviour = seats.iter_mut()
+                .find(|(seat, _)| *seat == player)
+                .map(|(_, behaviour)| behaviour.as_mut())
+                .expect("every player of the game is seated");
+
+            let start = Instant::now();
+            let code = match think(behaviour, game, clock.as_mut())? {
+                Some(code) => code,
+                None => break Outcome::forfeit(&game, player),
+            };
+
+            let stats = &mut stats[player.index()];
+            stats.moves += 1;
+            stats.thinking += start.elapsed();
+
+            let result = game.play(code);
+
+            // asking again would get the same move, the seat forfeits
+            if let PlayResult::Error = result {
+                eprintln!("{} played the illegal move {} and forfeits", player.name(), move_notation(code));
+                break Outcome::forfeit(&game, player);
+            }
+
+            moves.push_str(&move_notation(code));
+
+            for (seat, behaviour) in &mut seats {
+                if *seat != player {
+                    behaviour.opponent_played(code, game);
+                }
+            }
+
+            if let Some(broadcast) = &broadcast {
+                broadcast.played(code, game, clock);
+            }
+
+            match result {
+                PlayResult::Win(_) => break Outcome::Win(player),
+                PlayResult::Lose(lines) => break Outcome::Win(lines[0].player()),
+                PlayResult::Pass if game.stalemate() => break Outcome::Draw,
+                PlayResult::Pass | PlayResult::Error => (),
+            }
+        };
+
+        match outcome {
+            Outcome::Win(player) => score.record_win(player),
+            Outcome::Draw => score.record_draw(),
+            Outcome::Forfeit(player) => score.record_forfeit(player, game.players()),
+        }
+
+        if let Some(broadcast) = &broadcast {
+            broadcast.finished(match outcome {
+                Outcome::Win(player) => Some(player),
+                Outcome::Draw | Outcome::Forfeit(_) => None,
+            });
+        }
+
+        let result = match outcome {
+            Outcome::Win(player) => format!("{} wins", player.name()),
+            Outcome::Draw => String::from("draw"),
+            Outcome::Forfeit(player) => format!("{} forfeits, the others win", player.name()),
+        };
+
+        println!("game {}: {result} ({moves})", score.played());
+        game.restart();
+
+        for (_, behaviour) in &mut seats {
+            behaviour.new_game();
+        }
+    }
+
+    match options.players.as_slice() {
+        [first, second] => println!(
+            "{} {} - {} {}, {} draws",
+            first.name(), score.wins(*first), score.wins(*second), second.name(), score.draws(),
+        ),
+        players => {
+            let wins = players.iter().map(|&player| format!("{} {}", player.name(), score.wins(player)));
+            println!("{}, {} draws", wins.collect::<Vec<_>>().join(", "), score.draws());
+        }
+    }
+
+    print_stats(options, &score, &stats);
+    Ok(())
+}
+
+/// One line per seat with its share of the games won and its time per
+/// move, to compare behaviours and their settings.
+fn print_stats(options: &Options, score: &Score, stats: &[Stats; 4]) {
+    let games = score.played().max(1) as f32;
+    let moves = stats.iter().map(|stats| stats.moves).sum::<u32>();
+
+    println!("{:.1} moves per game", moves as f32 / games);
+
+    for &player in &options.players {
+        let Stats { moves, thinking } = stats[player.index()];
+        let wins = score.wins(player);
+        let per_move = thinking.as_secs_f32() * 1000.0 / moves.max(1) as f32;
+
+        println!(
+            "{:<8}{:<20}{wins:>5} wins {:>6.1}% {per_move:>10.1} ms per move",
+            player.name(), options.seat(player).name(), wins as f32 * 100.0 / games,
+        );
+    }
+}
+
+/// Ask `behaviour` for a move and wait for it. `None` means it ran out
+/// of time or resigned.
+fn think(behaviour: &mut dyn Behaviour, game: Game, clock: Option<&mut Clock>) -> Result<Option<u8>, String> {
+    let player = game.player_turn();
+    let start = Instant::now();
+
+    if let Some(clock) = &clock {
+        behaviour.time_left(clock.remaining(player));
+    }
+
+    behaviour.start_process(game);
+
+    let col = match behaviour.intent() {
+        Intent::Some(col) => col,
+        Intent::Waiting(handle) => handle.join().map_err(|_| format!("{} behaviour crashed", player.name()))?,
+        Intent::None => return Err(format!("{} behaviour did not play", player.name())),
+    };
+
+    if let Some(error) = behaviour.error() {
+        eprintln!("{}: {error}", player.name());
+    }
+
+    if col == RESIGN {
+        return Ok(None);
+    }
+
+    if let Some(clock) = clock {
+        clock.tick(start, None);
+
+        if clock.tick(Instant::now(), Some(player)).is_some() {
+            return Ok(None);
+        }
+
+        clock.press(player);
+    }
+
+    Ok(Some(col))
+}
@@ -0,0 +1,12 @@
+pub mod board_game;
+pub mod behaviour;
+pub mod animator;
+pub mod headless;
+pub mod cli;
+pub mod tui;
+pub mod protocol;
+pub mod net;
+pub mod search;
+pub mod book;
+pub mod database;
+pub mod analysis;
@@ -1,15 +1,42 @@
 use iced::{Application, window::{self, Icon}};
 use image::{GenericImageView, io::Reader as ImReader};
 
-// TODO: remove pub
-pub mod board_game;
-pub mod behaviour;
-pub mod animator;
-
-use board_game::Menu;
+use connect_four::board_game::Menu;
+use connect_four::cli::{self, Options};
+use connect_four::{headless, net, protocol, tui};
 
 pub fn main() -> iced::Result {
-    const WINDOW_SIZE: u32 = 720;
+    let options = match Options::parse(std::env::args().skip(1)) {
+        Ok(Some(options)) => options,
+        Ok(None) => {
+            println!("{}", cli::USAGE);
+            return Ok(());
+        }
+        Err(error) => {
+            eprintln!("error: {error}\n\n{}", cli::USAGE);
+            std::process::exit(2);
+        }
+    };
+
+    if options.headless || options.tui || options.engine.is_some() {
+        let result = if let (Some(engine), Some(address)) = (&options.engine, &options.client) {
+            let name = options.name.clone().unwrap_or_else(|| engine.name());
+            net::server::play(address, &name, engine.behaviour(), None)
+        } else if let Some(engine) = options.engine {
+            protocol::run(engine.behaviour())
+        } else if options.tui {
+            tui::run(&options)
+        } else {
+            headless::run(&options)
+        };
+
+        if let Err(error) = result {
+            eprintln!("error: {error}");
+            std::process::exit(1);
+        }
+
+        return Ok(());
+    }
 
     let path = "./icon/connect-four.png";
     let icon = match ImReader::open(path) {
@@ -25,13 +52,15 @@ pub fn main() -> iced::Result {
         _ => None,
     };
 
+    let window_size = options.window_size;
+
     Menu::run(iced::Settings {
         antialiasing: true,
         window: window::Settings {
-            size: (WINDOW_SIZE, WINDOW_SIZE),
+            size: (window_size, window_size),
             resizable: false, icon,
             .. Default::default()
         },
-        .. Default::default()
+        .. iced::Settings::with_flags(options)
     })
 }
@@ -0,0 +1,592 @@
+//! Live broadcast of a game to read-only spectators.
+//!
+//! ```text
+//! spectator > spectate 1
+//! host      > score 1 0 0 3             red, yellow, draws, best of or -
+//! host      > game 2 <board> r 4453     game, starting position, moves or -
+//! host      > move 2 5 3                game, ply, column
+//! host      > clock 61200 58000         remaining milliseconds, red then yellow
+//! host      > eval -0.20 0.05 ...       score of every column for the player to move
+//! host      > result 2 r                winner or draw
+//! ```
+//!
+//! A spectator joining mid-game receives the score, the game and every
+//! move played so far, then follows the live updates.
+
+use super::{coins, resolve, Connection, Inbox, Message, Remote, HANDSHAKE, POLL, RECONNECT, VERSION};
+use crate::behaviour::{Behaviour, Rollout};
+use crate::board_game::{Clock, Game, Player, Score};
+use crate::protocol::{decode_board, encode_board};
+
+use std::io::Write;
+use std::net::{SocketAddr, TcpListener, TcpStream};
+use std::sync::mpsc::{self, Receiver, TryRecvError};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+/// What a spectator is told about the broadcast game.
+pub enum Update {
+    /// A game begins or the spectator (re)joined: show `position`. The
+    /// moves that follow are played by the two seats.
+    Start { game: u32, position: Box<Game>, score: Score, red: Remote, yellow: Remote },
+    Clock { red: Duration, yellow: Duration },
+    Eval(Vec<f32>),
+    /// A line of the broadcast could not be read and was skipped.
+    Error(String),
+    /// The broadcast ended.
+    Lost,
+}
+
+enum Line {
+    Spectate { version: u32 },
+    Refused(String),
+    Score(Score),
+    Game { game: u32, position: Game, moves: Vec<u8> },
+    Move { game: u32, ply: u32, col: u8 },
+    Clock { red: Duration, yellow: Duration },
+    Eval(Vec<f32>),
+    Result { game: u32, winner: Option<Player> },
+}
+
+impl std::str::FromStr for Line {
+    type Err = String;
+
+    fn from_str(line: &str) -> Result<Self, Self::Err> {
+        let words = line.split_whitespace().collect::<Vec<_>>();
+        let number = |word: &str| word.parse::<u32>().map_err(|_| format!("invalid number '{word}'"));
+        let millis = |word: &str| word.parse().map(Duration::from_millis).map_err(|_| format!("invalid time '{word}'"));
+
+        match words.as_slice() {
+            ["spectate", version] => Ok(Line::Spectate { version: number(version)? }),
+            ["refused", ..] => Ok(Line::Refused(words[1..].join(" "))),
+            ["score", red, yellow, draws, best_of] => {
+                let mut score = match *best_of {
+                    "-" => Score::default(),
+                    games => Score::best_of(number(games)? as u16),
+                };
+
+                (0..number(red)?).for_each(|_| score.record_win(Player::Red));
+                (0..number(yellow)?).for_each(|_| score.record_win(Player::Yellow));
+                (0..number(draws)?).for_each(|_| score.record_draw());
+
+                Ok(Line::Score(score))
+            }
+            ["game", game, rows, turn, moves] => {
+                let moves = match *moves {
+                    "-" => Vec::new(),
+                    moves => moves.chars()
+                        .map(|col| match col.to_digit(10) {
+                            Some(col @ 1..) if col as usize <= Game::COL => Ok(col as u8 - 1),
+                            _ => Err(format!("invalid column '{col}'")),
+                        })
+                        .collect::<Result<_, _>>()?,
+                };
+
+                Ok(Line::Game { game: number(game)?, position: decode_board(rows, turn)?, moves })
+            }
+            ["move", ..] => match line.parse()? {
+                Message::Move { game, ply, col } => Ok(Line::Move { game, ply, col }),
+                _ => Err(format!("invalid move '{line}'")),
+            },
+            ["clock", red, yellow] => Ok(Line::Clock { red: millis(red)?, yellow: millis(yellow)? }),
+            ["eval", scores @ ..] if scores.len() == Game::COL => {
+                let eval = scores.iter()
+                    .map(|word| word.parse().map_err(|_| format!("invalid score '{word}'")))
+                    .collect::<Result<_, _>>()?;
+
+                Ok(Line::Eval(eval))
+            }
+            ["result", game, winner] => {
+                let winner = match *winner {
+                    "r" => Some(Player::Red),
+                    "y" => Some(Player::Yellow),
+                    "g" => Some(Player::Green),
+                    "b" => Some(Player::Blue),
+                    "draw" => None,
+                    _ => return Err(format!("invalid winner '{winner}'")),
+                };
+
+                Ok(Line::Result { game: number(game)?, winner })
+            }
+            _ => Err(format!("unknown line '{line}'")),
+        }
+    }
+}
+
+impl std::fmt::Display for Line {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Line::Spectate { version } => write!(f, "spectate {version}"),
+            Line::Refused(reason) => write!(f, "refused {reason}"),
+            Line::Score(score) => {
+                let best_of = score.match_length().map_or(String::from("-"), |games| games.to_string());
+                write!(f, "score {} {} {} {best_of}", score.wins(Player::Red), score.wins(Player::Yellow), score.draws())
+            }
+            Line::Game { game, position, moves } => {
+                let moves = match moves.is_empty() {
+                    true => String::from("-"),
+                    false => moves.iter().map(|col| (col + 1).to_string()).collect(),
+                };
+
+                write!(f, "game {game} {} {moves}", encode_board(position))
+            }
+            Line::Move { game, ply, col } => write!(f, "{}", Message::Move { game: *game, ply: *ply, col: *col }),
+            Line::Clock { red, yellow } => write!(f, "clock {} {}", red.as_millis(), yellow.as_millis()),
+            Line::Eval(scores) => {
+                let scores = scores.iter().map(|score| format!("{score:.2}")).collect::<Vec<_>>();
+                write!(f, "eval {}", scores.join(" "))
+            }
+            Line::Result { game, winner } => {
+                let winner = match winner {
+                    Some(Player::Red) => "r",
+                    Some(Player::Yellow) => "y",
+                    Some(Player::Green) => "g",
+                    Some(Player::Blue) => "b",
+                    None => "draw",
+                };
+
+                write!(f, "result {game} {winner}")
+            }
+        }
+    }
+}
+
+/// Host side: every event of the game is sent to the connected
+/// spectators, and kept so that late comers can catch up.
+pub struct Broadcast {
+    feed: Arc<Mutex<Feed>>,
+    address: SocketAddr,
+    positions: mpsc::Sender<((u32, usize), Game)>,
+}
+
+#[derive(Default)]
+struct Feed {
+    spectators: Vec<TcpStream>,
+
+    game: u32,
+    position: Game,
+    moves: Vec<u8>,
+    score: Score,
+    clock: Option<(Duration, Duration)>,
+    eval: Option<Vec<f32>>,
+    result: Option<Option<Player>>,
+}
+
+impl Feed {
+    fn snapshot(&self) -> Vec<Line> {
+        let mut lines = vec![
+            Line::Score(self.score),
+            Line::Game { game: self.game, position: self.position, moves: self.moves.clone() },
+        ];
+
+        if let Some((red, yellow)) = self.clock {
+            lines.push(Line::Clock { red, yellow });
+        }
+
+        if let Some(eval) = &self.eval {
+            lines.push(Line::Eval(eval.clone()));
+        }
+
+        if let Some(winner) = self.result {
+            lines.push(Line::Result { game: self.game, winner });
+        }
+
+        lines
+    }
+
+    fn send(&mut self, line: Line) {
+        // a spectator that cannot keep up is dropped
+        self.spectators.retain_mut(|stream| writeln!(stream, "{line}").and_then(|_| stream.flush()).is_ok());
+    }
+}
+
+impl Broadcast {
+    /// Accept spectators on `address` from now on.
+    pub fn listen(address: &str) -> Result<Self, String> {
+        let listener = TcpListener::bind(address).map_err(|error| format!("cannot listen on {address}: {error}"))?;
+        let address = listener.local_addr().map_err(|error| error.to_string())?;
+        let feed = Arc::new(Mutex::new(Feed::default()));
+
+        let shared = feed.clone();
+        std::thread::spawn(move || accept(listener, shared));
+
+        let (positions, pending) = mpsc::channel();
+        let shared = feed.clone();
+        std::thread::spawn(move || evaluate(pending, shared));
+
+        Ok(Self { feed, address, positions })
+    }
+
+    pub fn local_addr(&self) -> SocketAddr {
+        self.address
+    }
+
+    /// A game starts from `position`, `score` counting the games before it.
+    pub fn new_game(&self, position: Game, score: Score, clock: Option<Clock>) {
+        self.update(|feed| {
+            if feed.result.is_some() || !feed.moves.is_empty() {
+                feed.game += 1;
+            }
+
+            feed.position = position;
+            feed.moves.clear();
+            feed.score = score;
+            feed.clock = clock.map(|clock| (clock.remaining(Player::Red), clock.remaining(Player::Yellow)));
+            feed.eval = None;
+            feed.result = None;
+
+            for line in feed.snapshot() {
+                feed.send(line);
+            }
+        });
+
+        self.evaluate(position);
+    }
+
+    /// `col` was played leading to `state`, `clock` being pressed already.
+    pub fn played(&self, col: u8, state: Game, clock: Option<Clock>) {
+        self.update(|feed| {
+            feed.moves.push(col);
+            feed.eval = None;
+
+            let (game, ply) = (feed.game, coins(state));
+            feed.send(Line::Move { game, ply, col });
+
+            if let Some(clock) = clock {
+                let (red, yellow) = (clock.remaining(Player::Red), clock.remaining(Player::Yellow));
+
+                feed.clock = Some((red, yellow));
+                feed.send(Line::Clock { red, yellow });
+            }
+        });
+
+        self.evaluate(state);
+    }
+
+    /// The last `count` moves were taken back.
+    pub fn took_back(&self, count: usize) {
+        self.update(|feed| {
+            let kept = feed.moves.len().saturating_sub(count);
+            feed.moves.truncate(kept);
+            feed.eval = None;
+
+            for line in feed.snapshot() {
+                feed.send(line);
+            }
+        });
+    }
+
+    /// The game ended, `None` being a draw.
+    pub fn finished(&self, winner: Option<Player>) {
+        self.update(|feed| {
+            feed.result = Some(winner);
+            feed.send(Line::Result { game: feed.game, winner });
+        });
+    }
+
+    /// Have `state` evaluated aside.
+    fn evaluate(&self, state: Game) {
+        if let Some(ply) = self.read(|feed| (feed.game, feed.moves.len())) {
+            let _ = self.positions.send((ply, state));
+        }
+    }
+
+    fn update(&self, change: impl FnOnce(&mut Feed)) {
+        if let Ok(mut feed) = self.feed.lock() {
+            change(&mut feed);
+        }
+    }
+
+    fn read<T>(&self, read: impl FnOnce(&Feed) -> T) -> Option<T> {
+        self.feed.lock().ok().map(|feed| read(&feed))
+    }
+}
+
+/// Evaluate the latest position and send the scores if the game did not
+/// move on meanwhile.
+fn evaluate(pending: Receiver<((u32, usize), Game)>, feed: Arc<Mutex<Feed>>) {
+    let mut rollout = Rollout::default();
+
+    while let Ok(mut latest) = pending.recv() {
+        // positions played while evaluating are already outdated
+        while let Ok(newer) = pending.try_recv() {
+            latest = newer;
+        }
+
+        let (ply, state) = latest;
+        let Some(eval) = rollout.evaluate(state) else {
+            continue;
+        };
+
+        let Ok(mut feed) = feed.lock() else {
+            return;
+        };
+
+        if (feed.game, feed.moves.len()) == ply && feed.result.is_none() {
+            feed.eval = Some(eval.clone());
+            feed.send(Line::Eval(eval));
+        }
+    }
+}
+
+fn accept(listener: TcpListener, feed: Arc<Mutex<Feed>>) {
+    for stream in listener.incoming() {
+        let Ok(stream) = stream else { continue; };
+        let feed = feed.clone();
+
+        // shake hands aside, a slow spectator must not hold the others
+        std::thread::spawn(move || {
+            let Ok(mut connection) = Connection::new(stream) else { return; };
+
+            match connection.expect() {
+                Ok(Line::Spectate { version }) if version == VERSION => (),
+                Ok(Line::Spectate { version }) => {
+                    let _ = connection.send(&Line::Refused(format!("protocol version {version} is not {VERSION}")));
+                    return;
+                }
+                _ => {
+                    let _ = connection.send(&Line::Refused(String::from("expected spectate")));
+                    return;
+                }
+            }
+
+            let stream = connection.reader.into_inner();
+            if stream.set_write_timeout(Some(HANDSHAKE)).is_err() {
+                return;
+            }
+
+            if let Ok(mut feed) = feed.lock() {
+                let mut stream = Some(stream);
+
+                for line in feed.snapshot() {
+                    let sent = stream.as_mut().is_some_and(|stream| writeln!(stream, "{line}").is_ok());
+                    if !sent { stream = None; }
+                }
+
+                feed.spectators.extend(stream);
+            }
+        });
+    }
+}
+
+/// Spectator side, following the broadcast at an address.
+pub struct Spectator {
+    updates: Receiver<Update>,
+}
+
+impl Spectator {
+    /// Follow the broadcast at `address`, reconnecting when it drops.
+    pub fn connect(address: &str) -> Result<Self, String> {
+        let address = resolve(address)?;
+        let connection = spectate(address)?;
+
+        let (sender, updates) = mpsc::channel();
+
+        std::thread::spawn(move || {
+            let mut connection = Some(connection);
+            let mut follow = Follow::default();
+
+            loop {
+                let Some(mut current) = connection.take() else {
+                    let deadline = Instant::now() + RECONNECT;
+
+                    connection = loop {
+                        match spectate(address) {
+                            Ok(connection) => break Some(connection),
+                            Err(_) if Instant::now() < deadline => std::thread::sleep(POLL * 10),
+                            Err(_) => break None,
+                        }
+                    };
+
+                    if connection.is_none() {
+                        follow.inbox.close();
+                        let _ = sender.send(Update::Lost);
+                        return;
+                    }
+
+                    continue;
+                };
+
+                match current.receive() {
+                    Ok(Some(line)) => {
+                        let update = match line.parse() {
+                            Ok(line) => follow.line(line),
+                            Err(error) => Some(Update::Error(error)),
+                        };
+
+                        if update.is_some_and(|update| sender.send(update).is_err()) {
+                            return;
+                        }
+                    }
+                    Ok(None) => (),
+                    Err(_) => continue,
+                }
+
+                connection = Some(current);
+            }
+        });
+
+        Ok(Self { updates })
+    }
+
+    /// Next update, if any arrived.
+    pub fn poll(&mut self) -> Option<Update> {
+        match self.updates.try_recv() {
+            Ok(update) => Some(update),
+            Err(TryRecvError::Disconnected) => Some(Update::Lost),
+            Err(TryRecvError::Empty) => None,
+        }
+    }
+}
+
+/// What the spectator knows of the broadcast, the moves going straight to
+/// the seats of the current game.
+#[derive(Default)]
+struct Follow {
+    inbox: Arc<Inbox>,
+    score: Score,
+}
+
+impl Follow {
+    fn line(&mut self, line: Line) -> Option<Update> {
+        match line {
+            Line::Score(score) => self.score = score,
+            Line::Game { game, mut position, mut moves } => {
+                // the seats of the previous game keep the old inbox
+                self.inbox = Arc::default();
+
+                // the last move is played by the seats, it may end the game
+                let last = moves.pop();
+                for &col in &moves {
+                    position.play_col(col as usize);
+                }
+
+                if let Some(col) = last {
+                    self.inbox.receive(Message::Move { game, ply: coins(position) + 1, col });
+                }
+
+                let [red, yellow] = [(); 2].map(|_| {
+                    // nobody reads what the seats would send back
+                    let (outgoing, _) = mpsc::channel();
+                    Remote::new(outgoing, self.inbox.clone()).game(game)
+                });
+
+                return Some(Update::Start { game, position: Box::new(position), score: self.score, red, yellow });
+            }
+            Line::Move { game, ply, col } => self.inbox.receive(Message::Move { game, ply, col }),
+            Line::Result { game, winner: Some(_) } => self.inbox.receive(Message::Resign { game }),
+            Line::Result { .. } => (),
+            Line::Clock { red, yellow } => return Some(Update::Clock { red, yellow }),
+            Line::Eval(eval) => return Some(Update::Eval(eval)),
+            Line::Spectate { .. } | Line::Refused(_) => (),
+        }
+
+        None
+    }
+}
+
+fn spectate(address: SocketAddr) -> Result<Connection, String> {
+    let stream = TcpStream::connect_timeout(&address, HANDSHAKE)
+        .map_err(|error| format!("cannot connect to {address}: {error}"))?;
+
+    let mut connection = Connection::new(stream)?;
+    connection.send(&Line::Spectate { version: VERSION }).map_err(|error| error.to_string())?;
+
+    Ok(connection)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::behaviour::{Intent, RESIGN};
+
+    fn wait(seat: &mut Remote, state: Game) -> u8 {
+        seat.start_process(state);
+
+        match seat.intent() {
+            Intent::Some(col) => col,
+            Intent::Waiting(handle) => handle.join().unwrap(),
+            Intent::None => panic!("the seat did not play"),
+        }
+    }
+
+    /// Both seats are returned, dropping one closes the inbox they share.
+    fn start(spectator: &mut Spectator) -> (u32, Game, Score, Remote, Remote) {
+        let deadline = Instant::now() + HANDSHAKE;
+
+        while Instant::now() < deadline {
+            match spectator.poll() {
+                Some(Update::Start { game, position, score, red, yellow }) => return (game, *position, score, red, yellow),
+                Some(_) => (),
+                None => std::thread::sleep(POLL),
+            }
+        }
+
+        panic!("no game was received");
+    }
+
+    #[test]
+    fn spectators_join_mid_game() {
+        let broadcast = Broadcast::listen("127.0.0.1:0").unwrap();
+
+        let mut score = Score::best_of(3);
+        score.record_win(Player::Yellow);
+
+        let mut game = Game::default();
+        broadcast.new_game(game, score, None);
+
+        for col in [3, 2] {
+            game.play_col(col);
+            broadcast.played(col as u8, game, None);
+        }
+
+        let mut spectator = Spectator::connect(&broadcast.local_addr().to_string()).unwrap();
+        let (number, position, received, _red, mut seat) = start(&mut spectator);
+
+        // the last move is left to the seats so that it is shown
+        assert_eq!(number, 0);
+        assert_eq!(coins(position), 1);
+        assert_eq!(received.wins(Player::Yellow), 1);
+        assert_eq!(received.match_length(), Some(3));
+
+        assert_eq!(wait(&mut seat, position), 2);
+
+        game.play_col(4);
+        broadcast.played(4, game, None);
+        broadcast.finished(Some(Player::Red));
+
+        let mut before = game;
+        before.undo_col(4);
+
+        assert_eq!(wait(&mut seat, before), 4);
+        assert_eq!(wait(&mut seat, game), RESIGN);
+    }
+
+    #[test]
+    fn unreadable_lines_are_reported() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let address = listener.local_addr().unwrap();
+
+        std::thread::spawn(move || {
+            let (mut stream, _) = listener.accept().unwrap();
+            writeln!(stream, "checkmate").unwrap();
+            std::thread::sleep(HANDSHAKE);
+        });
+
+        let mut spectator = Spectator::connect(&address.to_string()).unwrap();
+        let deadline = Instant::now() + HANDSHAKE;
+
+        while Instant::now() < deadline {
+            match spectator.poll() {
+                Some(Update::Error(error)) => {
+                    assert_eq!(error, "unknown line 'checkmate'");
+                    return;
+                }
+                Some(_) => panic!("only an error was expected"),
+                None => std::thread::sleep(POLL),
+            }
+        }
+
+        panic!("no error was reported");
+    }
+}
@@ -0,0 +1,636 @@
+//! LAN play over TCP. Both ends exchange one message per line, columns are
+//! 1-based and every move carries its game and ply so that messages sent
+//! again after a reconnection are recognised.
+//!
+//! ```text
+//! guest > hello 1 7x6
+//! host  > welcome 1 7x6 r y         first player, seat of the guest
+//! host  > move 0 1 4                game, ply, column
+//! guest > move 0 2 4
+//! guest > resign 0
+//! host  > refused <reason>          instead of welcome
+//! ```
+//!
+//! When the connection drops, the host accepts a new one and the guest
+//! keeps connecting for a while. Both ends then shake hands again and send
+//! every message of the current game once more. A connection that cannot be
+//! restored counts as a resignation of the remote player. Problems of the
+//! connection are reported by the `error` of the `Remote` seat.
+
+mod broadcast;
+pub mod server;
+
+pub use broadcast::{Broadcast, Spectator, Update};
+
+use crate::behaviour::{Remote, RESIGN};
+use crate::board_game::{Game, Player};
+
+use std::io::{BufRead, BufReader, ErrorKind, Write};
+use std::net::{SocketAddr, TcpListener, TcpStream, ToSocketAddrs};
+use std::sync::mpsc::{self, Receiver, TryRecvError};
+use std::sync::{Arc, Condvar, Mutex};
+use std::time::{Duration, Instant};
+
+pub const VERSION: u32 = 1;
+pub const DEFAULT_ADDRESS: &str = "127.0.0.1:4444";
+
+/// How long a dropped connection may take to come back.
+const RECONNECT: Duration = Duration::from_secs(30);
+const HANDSHAKE: Duration = Duration::from_secs(5);
+const POLL: Duration = Duration::from_millis(50);
+
+#[derive(Clone, PartialEq, Eq)]
+pub enum Message {
+    Hello { version: u32, size: (usize, usize) },
+    Welcome { version: u32, size: (usize, usize), first: Player, guest: Player },
+    Move { game: u32, ply: u32, col: u8 },
+    Resign { game: u32 },
+    Refused(String),
+}
+
+impl Message {
+    fn game(&self) -> Option<u32> {
+        match self {
+            Message::Move { game, .. } | Message::Resign { game } => Some(*game),
+            _ => None,
+        }
+    }
+}
+
+impl std::str::FromStr for Message {
+    type Err = String;
+
+    fn from_str(line: &str) -> Result<Self, Self::Err> {
+        let words = line.split_whitespace().collect::<Vec<_>>();
+        let number = |word: &str| word.parse().map_err(|_| format!("invalid number '{word}'"));
+
+        match words.as_slice() {
+            ["hello", version, size] => Ok(Message::Hello { version: number(version)?, size: parse_size(size)? }),
+            ["welcome", version, size, first, guest] => Ok(Message::Welcome {
+                version: number(version)?,
+                size: parse_size(size)?,
+                first: parse_player(first)?,
+                guest: parse_player(guest)?,
+            }),
+            ["move", game, ply, col] => match col.parse::<u8>() {
+                Ok(col @ 1..) if (col as usize) <= Game::COL => {
+                    Ok(Message::Move { game: number(game)?, ply: number(ply)?, col: col - 1 })
+                }
+                _ => Err(format!("invalid column '{col}'")),
+            },
+            ["resign", game] => Ok(Message::Resign { game: number(game)? }),
+            ["refused", ..] => Ok(Message::Refused(words[1..].join(" "))),
+            _ => Err(format!("unknown message '{line}'")),
+        }
+    }
+}
+
+impl std::fmt::Display for Message {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Message::Hello { version, size: (cols, rows) } => write!(f, "hello {version} {cols}x{rows}"),
+            Message::Welcome { version, size: (cols, rows), first, guest } => {
+                write!(f, "welcome {version} {cols}x{rows} {} {}", player_code(*first), player_code(*guest))
+            }
+            Message::Move { game, ply, col } => write!(f, "move {game} {ply} {}", col + 1),
+            Message::Resign { game } => write!(f, "resign {game}"),
+            Message::Refused(reason) => write!(f, "refused {reason}"),
+        }
+    }
+}
+
+/// Both ends of a connected game, seen from this side.
+pub struct Session {
+    pub first: Player,
+    pub local: Player,
+    pub remote: Remote,
+}
+
+/// Moves and resignations received from the other end, waited on by the
+/// `Remote` seat.
+#[derive(Default)]
+pub struct Inbox {
+    state: Mutex<Received>,
+    changed: Condvar,
+}
+
+#[derive(Default)]
+struct Received {
+    moves: Vec<(u32, u32, u8)>,
+    resigned: Vec<u32>,
+    closed: bool,
+    /// Problems of the connection not reported yet, oldest first.
+    errors: Vec<String>,
+}
+
+impl Inbox {
+    /// Block until the column played at `ply` of `game` arrives, or
+    /// `RESIGN` when the other end gave up or cannot be reached anymore.
+    pub fn wait(&self, game: u32, ply: u32) -> u8 {
+        let Ok(mut state) = self.state.lock() else {
+            return RESIGN;
+        };
+
+        loop {
+            let played = state.moves.iter().find(|&&(g, p, _)| g == game && p == ply);
+            if let Some(&(_, _, col)) = played {
+                return col;
+            }
+
+            if state.closed || state.resigned.contains(&game) {
+                return RESIGN;
+            }
+
+            state = match self.changed.wait(state) {
+                Ok(state) => state,
+                Err(_) => return RESIGN,
+            };
+        }
+    }
+
+    /// Stop every wait, the other end is gone.
+    pub fn close(&self) {
+        self.update(|state| state.closed = true);
+    }
+
+    /// Oldest problem of the connection not reported yet.
+    pub fn error(&self) -> Option<String> {
+        let mut state = self.state.lock().ok()?;
+        (!state.errors.is_empty()).then(|| state.errors.remove(0))
+    }
+
+    fn report(&self, error: String) {
+        self.update(|state| state.errors.push(error));
+    }
+
+    fn receive(&self, message: Message) {
+        self.update(|state| match message {
+            Message::Move { game, ply, col } => {
+                // older games are over, only keep the current one
+                state.moves.retain(|&(g, _, _)| g >= game);
+
+                if !state.moves.contains(&(game, ply, col)) {
+                    state.moves.push((game, ply, col));
+                }
+            }
+            Message::Resign { game } => state.resigned.push(game),
+            _ => (),
+        });
+    }
+
+    fn update(&self, change: impl FnOnce(&mut Received)) {
+        if let Ok(mut state) = self.state.lock() {
+            change(&mut state);
+            self.changed.notify_all();
+        }
+    }
+}
+
+/// Listening side of a game, waiting for a guest to join.
+pub struct Host {
+    listener: TcpListener,
+}
+
+impl Host {
+    pub fn bind(address: &str) -> Result<Self, String> {
+        let listener = TcpListener::bind(address).map_err(|error| format!("cannot listen on {address}: {error}"))?;
+        Ok(Self { listener })
+    }
+
+    pub fn local_addr(&self) -> Result<SocketAddr, String> {
+        self.listener.local_addr().map_err(|error| error.to_string())
+    }
+
+    /// Wait for a guest, offering it the seat `local` does not take. Fail
+    /// when the first guest cannot shake hands.
+    pub fn accept(self, first: Player, local: Player) -> Result<Session, String> {
+        let guest = local.opponent();
+
+        let (stream, _) = self.listener.accept().map_err(|error| format!("cannot accept: {error}"))?;
+        let mut connection = Connection::new(stream)?;
+        welcome(&mut connection, first, guest)?;
+
+        let endpoint = Endpoint::Host { listener: self.listener, first, guest };
+        Ok(Session { first, local, remote: start_link(endpoint, connection) })
+    }
+}
+
+/// Join the game hosted at `address`.
+pub fn join(address: &str) -> Result<Session, String> {
+    let address = resolve(address)?;
+    let stream = TcpStream::connect_timeout(&address, HANDSHAKE)
+        .map_err(|error| format!("cannot connect to {address}: {error}"))?;
+
+    let mut connection = Connection::new(stream)?;
+    let (first, local) = hello(&mut connection)?;
+
+    let endpoint = Endpoint::Guest { address, first, local };
+    Ok(Session { first, local, remote: start_link(endpoint, connection) })
+}
+
+fn start_link(endpoint: Endpoint, connection: Connection) -> Remote {
+    let (sender, outgoing) = mpsc::channel();
+    let inbox = Arc::new(Inbox::default());
+
+    let received = inbox.clone();
+    std::thread::spawn(move || link(endpoint, connection, outgoing, received));
+
+    Remote::new(sender, inbox)
+}
+
+/// Forward local messages and receive remote ones until the `Remote` seat
+/// is dropped or the connection is lost for good.
+fn link(endpoint: Endpoint, mut connection: Connection, outgoing: Receiver<Message>, inbox: Arc<Inbox>) {
+    // messages of the current game, sent again after a reconnection
+    let mut sent: Vec<Message> = Vec::new();
+
+    loop {
+        let mut healthy = loop {
+            match outgoing.try_recv() {
+                Ok(message) => {
+                    if message.game() > sent.last().and_then(Message::game) {
+                        sent.clear();
+                    }
+
+                    let healthy = connection.send(&message).is_ok();
+                    sent.push(message);
+
+                    if !healthy {
+                        break false;
+                    }
+                }
+                Err(TryRecvError::Empty) => break true,
+                Err(TryRecvError::Disconnected) => return,
+            }
+        };
+
+        if healthy {
+            match connection.receive() {
+                Ok(Some(line)) => match line.parse() {
+                    Ok(Message::Refused(reason)) => {
+                        inbox.report(format!("refused: {reason}"));
+                        healthy = false;
+                    }
+                    Ok(message) => inbox.receive(message),
+                    Err(error) => inbox.report(error),
+                },
+                Ok(None) => (),
+                Err(_) => healthy = false,
+            }
+        }
+
+        if !healthy {
+            match endpoint.reconnect(&inbox) {
+                Ok(restored) => {
+                    connection = restored;
+
+                    // the other end ignores what it already received
+                    if sent.iter().any(|message| connection.send(message).is_err()) {
+                        continue;
+                    }
+                }
+                Err(error) => {
+                    inbox.report(format!("connection lost: {error}"));
+                    inbox.close();
+                    return;
+                }
+            }
+        }
+    }
+}
+
+enum Endpoint {
+    Host { listener: TcpListener, first: Player, guest: Player },
+    Guest { address: SocketAddr, first: Player, local: Player },
+}
+
+impl Endpoint {
+    /// A new connection, the failed handshakes reported to `inbox`.
+    fn reconnect(&self, inbox: &Inbox) -> Result<Connection, String> {
+        let deadline = Instant::now() + RECONNECT;
+
+        match self {
+            Endpoint::Host { listener, first, guest } => {
+                listener.set_nonblocking(true).map_err(|error| error.to_string())?;
+
+                let connection = loop {
+                    if Instant::now() > deadline {
+                        break Err(String::from("no guest came back"));
+                    }
+
+                    let stream = match listener.accept() {
+                        Ok((stream, _)) => stream,
+                        Err(error) if error.kind() == ErrorKind::WouldBlock => {
+                            std::thread::sleep(POLL);
+                            continue;
+                        }
+                        Err(error) => break Err(error.to_string()),
+                    };
+
+                    let attempt = stream.set_nonblocking(false)
+                        .map_err(|error| error.to_string())
+                        .and_then(|_| Connection::new(stream));
+
+                    let Ok(mut connection) = attempt else { continue; };
+
+                    match welcome(&mut connection, *first, *guest) {
+                        Ok(()) => break Ok(connection),
+                        Err(error) => inbox.report(error),
+                    }
+                };
+
+                listener.set_nonblocking(false).map_err(|error| error.to_string())?;
+                connection
+            }
+            Endpoint::Guest { address, first, local } => loop {
+                let attempt = TcpStream::connect_timeout(address, HANDSHAKE)
+                    .map_err(|error| error.to_string())
+                    .and_then(Connection::new)
+                    .and_then(|mut connection| Ok((hello(&mut connection)?, connection)));
+
+                match attempt {
+                    Ok(((agreed_first, agreed_local), connection)) if agreed_first == *first && agreed_local == *local => {
+                        return Ok(connection);
+                    }
+                    Ok(_) => return Err(String::from("the host changed the seats")),
+                    Err(_) if Instant::now() < deadline => std::thread::sleep(POLL * 10),
+                    Err(error) => return Err(error),
+                }
+            },
+        }
+    }
+}
+
+/// Host side of the handshake.
+fn welcome(connection: &mut Connection, first: Player, guest: Player) -> Result<(), String> {
+    let refuse = |connection: &mut Connection, reason: String| {
+        let _ = connection.send(&Message::Refused(reason.clone()));
+        Err(reason)
+    };
+
+    match connection.expect()? {
+        Message::Hello { version, .. } if version != VERSION => {
+            refuse(connection, format!("protocol version {version} is not {VERSION}"))
+        }
+        Message::Hello { size, .. } if size != (Game::COL, Game::ROW) => {
+            refuse(connection, format!("board size {}x{} is not {}x{}", size.0, size.1, Game::COL, Game::ROW))
+        }
+        Message::Hello { .. } => {
+            let welcome = Message::Welcome { version: VERSION, size: (Game::COL, Game::ROW), first, guest };
+            connection.send(&welcome).map_err(|error| error.to_string())
+        }
+        message => refuse(connection, format!("expected hello, got '{message}'")),
+    }
+}
+
+/// Guest side of the handshake, returns the first player and the seat of
+/// the guest.
+fn hello(connection: &mut Connection) -> Result<(Player, Player), String> {
+    let hello = Message::Hello { version: VERSION, size: (Game::COL, Game::ROW) };
+    connection.send(&hello).map_err(|error| error.to_string())?;
+
+    match connection.expect()? {
+        Message::Welcome { version, size, first, guest } if version == VERSION && size == (Game::COL, Game::ROW) => {
+            Ok((first, guest))
+        }
+        Message::Welcome { version, size: (cols, rows), .. } => {
+            Err(format!("the host plays version {version} on {cols}x{rows}"))
+        }
+        Message::Refused(reason) => Err(format!("refused: {reason}")),
+        message => Err(format!("expected welcome, got '{message}'")),
+    }
+}
+
+struct Connection {
+    reader: BufReader<TcpStream>,
+    pending: Vec<u8>,
+}
+
+impl Connection {
+    fn new(stream: TcpStream) -> Result<Self, String> {
+        stream.set_read_timeout(Some(POLL)).map_err(|error| error.to_string())?;
+        let _ = stream.set_nodelay(true);
+
+        Ok(Self { reader: BufReader::new(stream), pending: Vec::new() })
+    }
+
+    fn send(&mut self, message: &impl std::fmt::Display) -> std::io::Result<()> {
+        let stream = self.reader.get_mut();
+        writeln!(stream, "{message}").and_then(|_| stream.flush())
+    }
+
+    /// Next complete line, `None` when nothing arrived in time.
+    fn receive(&mut self) -> std::io::Result<Option<String>> {
+        match self.reader.read_until(b'\n', &mut self.pending) {
+            Ok(0) => Err(ErrorKind::UnexpectedEof.into()),
+            Ok(_) if self.pending.ends_with(b"\n") => {
+                let line = String::from_utf8_lossy(&self.pending).trim().to_string();
+                self.pending.clear();
+
+                Ok(Some(line))
+            }
+            Ok(_) => Ok(None),
+            Err(error) if matches!(error.kind(), ErrorKind::WouldBlock | ErrorKind::TimedOut) => Ok(None),
+            Err(error) => Err(error),
+        }
+    }
+
+    /// Next message during the handshake.
+    fn expect<M: std::str::FromStr<Err = String>>(&mut self) -> Result<M, String> {
+        self.line()?.parse()
+    }
+
+    /// Next line, waiting up to the handshake timeout.
+    fn line(&mut self) -> Result<String, String> {
+        let deadline = Instant::now() + HANDSHAKE;
+
+        while Instant::now() < deadline {
+            match self.receive() {
+                Ok(Some(line)) => return Ok(line),
+                Ok(None) => (),
+                Err(error) => return Err(format!("handshake failed: {error}")),
+            }
+        }
+
+        Err(String::from("handshake timed out"))
+    }
+}
+
+/// Number of coins in the grid, the ply of the last move.
+pub fn coins(state: Game) -> u32 {
+    state.grid().iter().filter(|cell| cell.is_some()).count() as u32
+}
+
+fn resolve(address: &str) -> Result<SocketAddr, String> {
+    address.to_socket_addrs().ok()
+        .and_then(|mut addresses| addresses.next())
+        .ok_or_else(|| format!("invalid address '{address}'"))
+}
+
+fn parse_size(size: &str) -> Result<(usize, usize), String> {
+    size.split_once('x')
+        .and_then(|(cols, rows)| Some((cols.parse().ok()?, rows.parse().ok()?)))
+        .ok_or_else(|| format!("invalid board size '{size}'"))
+}
+
+fn parse_player(player: &str) -> Result<Player, String> {
+    match player {
+        "r" => Ok(Player::Red),
+        "y" => Ok(Player::Yellow),
+        "g" => Ok(Player::Green),
+        "b" => Ok(Player::Blue),
+        _ => Err(format!("invalid player '{player}'")),
+    }
+}
+
+fn player_code(player: Player) -> char {
+    match player {
+        Player::Red => 'r',
+        Player::Yellow => 'y',
+        Player::Green => 'g',
+        Player::Blue => 'b',
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::behaviour::{Behaviour, Intent};
+
+    fn wait(remote: &mut Remote, state: Game) -> u8 {
+        remote.start_process(state);
+
+        match remote.intent() {
+            Intent::Some(col) => col,
+            Intent::Waiting(handle) => handle.join().unwrap(),
+            Intent::None => panic!("the remote seat did not play"),
+        }
+    }
+
+    fn connect() -> (Session, Session) {
+        let host = Host::bind("127.0.0.1:0").unwrap();
+        let address = host.local_addr().unwrap().to_string();
+
+        let accepted = std::thread::spawn(move || host.accept(Player::Yellow, Player::Red));
+        let guest = join(&address).unwrap();
+
+        (accepted.join().unwrap().unwrap(), guest)
+    }
+
+    #[test]
+    fn handshake_moves_and_resign() {
+        let (mut host, mut guest) = connect();
+
+        assert!((host.first, host.local) == (Player::Yellow, Player::Red));
+        assert!((guest.first, guest.local) == (Player::Yellow, Player::Yellow));
+
+        // the guest plays yellow and starts
+        let mut game = Game::new(Player::Yellow);
+        game.play_col(3);
+        guest.remote.opponent_played(3, game);
+        assert_eq!(wait(&mut host.remote, Game::new(Player::Yellow)), 3);
+
+        let before = game;
+        game.play_col(2);
+        host.remote.opponent_played(2, game);
+        assert_eq!(wait(&mut guest.remote, before), 2);
+
+        guest.remote.opponent_resigned();
+        assert_eq!(wait(&mut host.remote, game), RESIGN);
+
+        // the next game starts from ply 1 again
+        host.remote.new_game();
+        guest.remote.new_game();
+
+        let mut game = Game::new(Player::Yellow);
+        game.play_col(6);
+        guest.remote.opponent_played(6, game);
+        assert_eq!(wait(&mut host.remote, Game::new(Player::Yellow)), 6);
+    }
+
+    #[test]
+    fn other_versions_are_refused() {
+        let host = Host::bind("127.0.0.1:0").unwrap();
+        let address = host.local_addr().unwrap();
+        let accepted = std::thread::spawn(move || host.accept(Player::Red, Player::Red));
+
+        let mut connection = Connection::new(TcpStream::connect(address).unwrap()).unwrap();
+        connection.send(&Message::Hello { version: VERSION + 1, size: (Game::COL, Game::ROW) }).unwrap();
+
+        assert!(matches!(connection.expect(), Ok(Message::Refused(_))));
+        assert_eq!(accepted.join().unwrap().err(), Some(format!("protocol version {} is not {VERSION}", VERSION + 1)));
+    }
+
+    #[test]
+    fn unknown_messages_are_reported_by_the_remote_seat() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let address = listener.local_addr().unwrap().to_string();
+        let guest = std::thread::spawn(move || join(&address));
+
+        let mut host = Connection::new(listener.accept().unwrap().0).unwrap();
+        welcome(&mut host, Player::Red, Player::Yellow).unwrap();
+        let mut guest = guest.join().unwrap().unwrap();
+
+        host.send(&"shuffle 0").unwrap();
+        let deadline = Instant::now() + HANDSHAKE;
+
+        let error = loop {
+            match guest.remote.error() {
+                Some(error) => break error,
+                None if Instant::now() < deadline => std::thread::sleep(POLL),
+                None => panic!("nothing reported"),
+            }
+        };
+
+        assert_eq!(error, "unknown message 'shuffle 0'");
+        assert_eq!(guest.remote.error(), None);
+    }
+
+    #[test]
+    fn the_host_waits_for_the_guest_to_come_back() {
+        let host = Host::bind("127.0.0.1:0").unwrap();
+        let address = host.local_addr().unwrap();
+        let accepted = std::thread::spawn(move || host.accept(Player::Red, Player::Red));
+
+        let shake = || {
+            let mut guest = Connection::new(TcpStream::connect(address).unwrap()).unwrap();
+            assert!(hello(&mut guest).unwrap() == (Player::Red, Player::Yellow));
+            guest
+        };
+
+        let mut guest = shake();
+        let mut host = accepted.join().unwrap().unwrap();
+
+        let mut game = Game::new(Player::Red);
+        game.play_col(3);
+        host.remote.opponent_played(3, game);
+        assert!(guest.expect() == Ok(Message::Move { game: 0, ply: 1, col: 3 }));
+
+        drop(guest);
+        let mut guest = shake();
+
+        // the move may have been lost with the connection, it comes again
+        assert!(guest.expect() == Ok(Message::Move { game: 0, ply: 1, col: 3 }));
+
+        guest.send(&Message::Move { game: 0, ply: 2, col: 4 }).unwrap();
+        assert_eq!(wait(&mut host.remote, game), 4);
+    }
+
+    #[test]
+    fn messages_round_trip() {
+        let messages = [
+            Message::Hello { version: VERSION, size: (7, 6) },
+            Message::Welcome { version: VERSION, size: (7, 6), first: Player::Red, guest: Player::Yellow },
+            Message::Move { game: 2, ply: 11, col: 6 },
+            Message::Resign { game: 0 },
+            Message::Refused(String::from("board size 8x7 is not 7x6")),
+        ];
+
+        for message in messages {
+            assert!(message.to_string().parse::<Message>() == Ok(message));
+        }
+
+        assert!("move 0 1 8".parse::<Message>().is_err());
+        assert!("move 0 1 0".parse::<Message>().is_err());
+    }
+}
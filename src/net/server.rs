@@ -0,0 +1,507 @@
+//! Ladder server: clients queue up and are paired into games refereed by
+//! the server, one after the other, until they leave.
+//!
+//! ```text
+//! client > join 1 <name>
+//! server > queued
+//! server > start 12 r <opponent>            game, seat, opponent name
+//! server > go <board> r 59000               position, remaining milliseconds
+//! client > move 4                           or resign
+//! server > illegal 4                        the move was refused, play again
+//! server > moved 3                          move of the opponent
+//! server > result win|loss|draw|aborted <reason>
+//! ```
+//!
+//! Every finished game is appended to the result log.
+
+use super::{resolve, Connection, HANDSHAKE, VERSION};
+use crate::behaviour::{Behaviour, Intent, RESIGN};
+use crate::board_game::{Clock, Game, PlayResult, Player};
+use crate::protocol::{decode_board, encode_board};
+
+use std::collections::VecDeque;
+use std::io::Write;
+use std::net::{SocketAddr, TcpListener, TcpStream};
+use std::path::PathBuf;
+use std::sync::{Arc, Condvar, Mutex};
+use std::time::{Duration, Instant, SystemTime};
+
+pub const DEFAULT_ADDRESS: &str = "127.0.0.1:5000";
+
+pub struct Server {
+    listener: TcpListener,
+    clock: Clock,
+    log: PathBuf,
+}
+
+struct Client {
+    name: String,
+    connection: Connection,
+}
+
+impl Client {
+    fn send(&mut self, line: String) -> Result<(), String> {
+        self.connection.send(&line).map_err(|error| error.to_string())
+    }
+}
+
+/// Waiting clients, paired in their order of arrival.
+#[derive(Default)]
+struct Queue {
+    clients: Mutex<VecDeque<Client>>,
+    changed: Condvar,
+}
+
+impl Queue {
+    fn push(&self, mut client: Client) {
+        let Ok(mut clients) = self.clients.lock() else {
+            return;
+        };
+
+        // confirm under the lock, a client told it is queued is ahead of
+        // the ones connecting afterwards
+        if client.send(String::from("queued")).is_ok() {
+            clients.push_back(client);
+            self.changed.notify_all();
+        }
+    }
+
+    fn pair(&self) -> Option<(Client, Client)> {
+        let mut clients = self.clients.lock().ok()?;
+
+        loop {
+            if clients.len() >= 2 {
+                return Some((clients.pop_front()?, clients.pop_front()?));
+            }
+
+            clients = self.changed.wait(clients).ok()?;
+        }
+    }
+}
+
+enum End {
+    Win(Player, &'static str),
+    Draw,
+}
+
+impl Server {
+    /// Listen on `address`, every game being played with `clock` and
+    /// appended to `log`.
+    pub fn bind(address: &str, clock: Clock, log: impl Into<PathBuf>) -> Result<Self, String> {
+        let listener = TcpListener::bind(address).map_err(|error| format!("cannot listen on {address}: {error}"))?;
+        Ok(Self { listener, clock, log: log.into() })
+    }
+
+    pub fn local_addr(&self) -> Result<SocketAddr, String> {
+        self.listener.local_addr().map_err(|error| error.to_string())
+    }
+
+    /// Accept clients and referee their games, forever. `report` is told
+    /// of refused clients and of results the log could not take.
+    pub fn run(self, report: impl Fn(String) + Send + Sync + 'static) -> Result<(), String> {
+        let queue = Arc::new(Queue::default());
+        let log = Arc::new(Mutex::new(self.log));
+        let report = Arc::new(report);
+        let clock = self.clock;
+
+        let (waiting, referee_report) = (queue.clone(), report.clone());
+        std::thread::spawn(move || {
+            let mut number = 0;
+
+            while let Some(pair) = waiting.pair() {
+                number += 1;
+
+                let (queue, log, report) = (waiting.clone(), log.clone(), referee_report.clone());
+                std::thread::spawn(move || referee(number, pair, clock, &queue, &log, &*report));
+            }
+        });
+
+        for stream in self.listener.incoming() {
+            let Ok(stream) = stream else { continue; };
+            let (queue, report) = (queue.clone(), report.clone());
+
+            std::thread::spawn(move || match introduce(stream) {
+                Ok(client) => queue.push(client),
+                Err(error) => report(format!("refused a client: {error}")),
+            });
+        }
+
+        Ok(())
+    }
+}
+
+fn introduce(stream: TcpStream) -> Result<Client, String> {
+    let mut connection = Connection::new(stream)?;
+    let line = connection.line()?;
+    let words = line.split_whitespace().collect::<Vec<_>>();
+
+    let refuse = |connection: &mut Connection, reason: String| {
+        let _ = connection.send(&format!("refused {reason}"));
+        Err(reason)
+    };
+
+    match words.as_slice() {
+        ["join", version, name] if *version == VERSION.to_string() => {
+            Ok(Client { name: name.to_string(), connection })
+        }
+        ["join", version, _] => refuse(&mut connection, format!("protocol version {version} is not {VERSION}")),
+        _ => refuse(&mut connection, String::from("expected join <version> <name>")),
+    }
+}
+
+/// Play one game between `red` and `yellow`, then queue them again.
+fn referee(number: u32, (mut red, mut yellow): (Client, Client), mut clock: Clock, queue: &Queue, log: &Mutex<PathBuf>, report: &dyn Fn(String)) {
+    let _ = red.send(format!("start {number} r {}", yellow.name));
+    let _ = yellow.send(format!("start {number} y {}", red.name));
+
+    let mut game = Game::default();
+    let mut moves = String::new();
+    let mut gone = None;
+
+    let end = loop {
+        let player = game.player_turn();
+        let (client, opponent) = match player {
+            Player::Red => (&mut red, &mut yellow),
+            _ => (&mut yellow, &mut red),
+        };
+
+        let start = Instant::now();
+        let remaining = clock.remaining(player);
+        clock.tick(start, None);
+
+        if client.send(format!("go {} {}", encode_board(&game), remaining.as_millis())).is_err() {
+            gone = Some(player);
+            break End::Win(player.opponent(), "disconnection");
+        }
+
+        // refused moves are played again on the same clock
+        let played = loop {
+            match next_move(client, start + remaining) {
+                Ok(Some(col)) => match game.play_col(col) {
+                    PlayResult::Error => {
+                        let _ = client.send(format!("illegal {}", col + 1));
+                    }
+                    result => break Ok((col, result)),
+                },
+                Ok(None) => break Err("resignation"),
+                Err(reason) => break Err(reason),
+            }
+        };
+
+        let (col, result) = match played {
+            Ok(played) => played,
+            Err(reason) => {
+                if reason == "disconnection" {
+                    gone = Some(player);
+                }
+
+                break End::Win(player.opponent(), reason);
+            }
+        };
+
+        if clock.tick(Instant::now(), Some(player)).is_some() {
+            break End::Win(player.opponent(), "time");
+        }
+
+        clock.press(player);
+        moves.push_str(&(col + 1).to_string());
+
+        if opponent.send(format!("moved {}", col + 1)).is_err() {
+            gone = Some(player.opponent());
+        }
+
+        match result {
+            PlayResult::Win(_) => break End::Win(player, "four"),
+            PlayResult::Pass if game.grid_full() => break End::Draw,
+            _ => (),
+        }
+    };
+
+    let (result, reason) = match end {
+        End::Win(player, reason) => (player.name(), reason),
+        End::Draw => ("draw", "full"),
+    };
+
+    // a client leaving before the first move aborts the game
+    let aborted = moves.is_empty() && gone.is_some();
+
+    if !aborted {
+        let time = SystemTime::now()
+            .duration_since(SystemTime::UNIX_EPOCH)
+            .map_or(0, |time| time.as_secs());
+
+        let moves = if moves.is_empty() { "-" } else { &moves };
+        let entry = format!("{time} {number} {} {} {result} {reason} {moves}", red.name, yellow.name);
+
+        if let Err(error) = append(log, &entry) {
+            report(format!("cannot log '{entry}': {error}"));
+        }
+    }
+
+    // yellow queues first so that colours alternate on a rematch
+    for (mut client, player) in [(yellow, Player::Yellow), (red, Player::Red)] {
+        if gone == Some(player) {
+            continue;
+        }
+
+        let outcome = match end {
+            _ if aborted => "aborted",
+            End::Win(winner, _) if winner == player => "win",
+            End::Win(..) => "loss",
+            End::Draw => "draw",
+        };
+
+        if client.send(format!("result {outcome} {reason}")).is_ok() {
+            queue.push(client);
+        }
+    }
+}
+
+/// Wait for the column of `client` until `deadline`. `None` means it
+/// resigned, errors are the reason it lost.
+fn next_move(client: &mut Client, deadline: Instant) -> Result<Option<usize>, &'static str> {
+    while Instant::now() < deadline {
+        let line = match client.connection.receive() {
+            Ok(Some(line)) => line,
+            Ok(None) => continue,
+            Err(_) => return Err("disconnection"),
+        };
+
+        match line.split_whitespace().collect::<Vec<_>>().as_slice() {
+            ["resign"] => return Ok(None),
+            ["move", col] => match col.parse::<usize>() {
+                Ok(col @ 1..=Game::COL) => return Ok(Some(col - 1)),
+                _ => {
+                    let _ = client.send(format!("illegal {col}"));
+                }
+            },
+            _ => {
+                let _ = client.send(format!("error unknown command '{line}'"));
+            }
+        }
+    }
+
+    Err("time")
+}
+
+fn append(log: &Mutex<PathBuf>, entry: &str) -> Result<(), String> {
+    let path = log.lock().map_err(|_| String::from("log poisoned"))?;
+    let mut file = std::fs::OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(&*path)
+        .map_err(|error| error.to_string())?;
+
+    writeln!(file, "{entry}").map_err(|error| error.to_string())
+}
+
+/// Move of `engine` from `game`, as sent to the server.
+fn reply(engine: &mut dyn Behaviour, game: Game) -> Result<String, String> {
+    engine.start_process(game);
+
+    let col = match engine.intent() {
+        Intent::Some(col) => col,
+        Intent::Waiting(handle) => handle.join().map_err(|_| String::from("engine crashed"))?,
+        Intent::None => return Err(String::from("the engine did not play")),
+    };
+
+    Ok(match col {
+        RESIGN => String::from("resign"),
+        col => format!("move {}", col + 1),
+    })
+}
+
+/// Play `engine` on the server at `address` under `name`, for `games`
+/// games or until the server goes away. A refused move is asked again to
+/// the engine.
+pub fn play(address: &str, name: &str, mut engine: Box<dyn Behaviour>, games: Option<u32>) -> Result<(), String> {
+    let address = resolve(address)?;
+    let stream = TcpStream::connect_timeout(&address, HANDSHAKE)
+        .map_err(|error| format!("cannot connect to {address}: {error}"))?;
+
+    let mut connection = Connection::new(stream)?;
+    connection.send(&format!("join {VERSION} {name}")).map_err(|error| error.to_string())?;
+
+    let mut played = 0;
+    let mut position = None;
+
+    loop {
+        let line = match connection.receive() {
+            Ok(Some(line)) => line,
+            Ok(None) => continue,
+            Err(_) if played > 0 => return Ok(()),
+            Err(error) => return Err(format!("connection lost: {error}")),
+        };
+
+        let words = line.split_whitespace().collect::<Vec<_>>();
+
+        match words.as_slice() {
+            ["refused", ..] => return Err(line),
+            ["start", number, _, opponent] => {
+                println!("game {number} against {opponent}");
+                engine.new_game();
+            }
+            ["go", rows, turn, millis] => {
+                let game = decode_board(rows, turn)?;
+                position = Some(game);
+
+                if let Ok(millis) = millis.parse() {
+                    engine.time_left(Duration::from_millis(millis));
+                }
+
+                let reply = reply(engine.as_mut(), game)?;
+                connection.send(&reply).map_err(|error| error.to_string())?;
+            }
+            // the server waits for another move on the same clock
+            ["illegal", _] => {
+                let game = position.ok_or_else(|| format!("'{line}' before any position"))?;
+                let reply = reply(engine.as_mut(), game)?;
+                connection.send(&reply).map_err(|error| error.to_string())?;
+            }
+            ["result", "aborted", ..] => println!("{line}"),
+            ["result", ..] => {
+                println!("{line}");
+                played += 1;
+
+                if games.is_some_and(|games| played >= games) {
+                    return Ok(());
+                }
+            }
+            _ => (),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::behaviour::Random;
+
+    fn log_path(test: &str) -> PathBuf {
+        let path = std::env::temp_dir().join(format!("connect-four-{}-{test}.log", std::process::id()));
+        let _ = std::fs::remove_file(&path);
+        path
+    }
+
+    fn raw_client(address: SocketAddr, name: &str) -> Connection {
+        let mut connection = Connection::new(TcpStream::connect(address).unwrap()).unwrap();
+        connection.send(&format!("join {VERSION} {name}")).unwrap();
+        assert_eq!(connection.line().unwrap(), "queued");
+        connection
+    }
+
+    #[test]
+    fn bots_play_and_results_are_logged() {
+        let log = log_path("ladder");
+        let server = Server::bind("127.0.0.1:0", Clock::new(Duration::from_secs(5), Duration::ZERO), &log).unwrap();
+        let address = server.local_addr().unwrap().to_string();
+        std::thread::spawn(move || server.run(|_| ()));
+
+        let bots = ["alpha", "beta"].map(|name| {
+            let address = address.clone();
+            std::thread::spawn(move || play(&address, name, Box::<Random>::default(), Some(3)))
+        });
+
+        for bot in bots {
+            bot.join().unwrap().unwrap();
+        }
+
+        let entries = std::fs::read_to_string(&log).unwrap();
+        assert_eq!(entries.lines().count(), 3);
+
+        for entry in entries.lines() {
+            let fields = entry.split(' ').collect::<Vec<_>>();
+
+            assert!(fields[2] != fields[3]);
+            assert!(["red", "yellow", "draw"].contains(&fields[4]));
+        }
+    }
+
+    /// Plays its moves in order, whatever the position.
+    struct Scripted(Vec<u8>);
+
+    impl Behaviour for Scripted {
+        fn start_process(&mut self, _state: Game) {
+        }
+
+        fn intent(&mut self) -> Intent {
+            match self.0.is_empty() {
+                true => Intent::None,
+                false => Intent::Some(self.0.remove(0)),
+            }
+        }
+    }
+
+    #[test]
+    fn clients_play_again_after_an_illegal_move() {
+        let server = Server::bind("127.0.0.1:0", Clock::new(Duration::from_secs(5), Duration::ZERO), log_path("again")).unwrap();
+        let address = server.local_addr().unwrap();
+        std::thread::spawn(move || server.run(|_| ()));
+
+        // the first client to queue plays red
+        let mut red = raw_client(address, "red");
+        let yellow = std::thread::spawn(move || play(&address.to_string(), "yellow", Box::new(Scripted(vec![8, 3])), Some(1)));
+
+        assert!(red.line().unwrap().starts_with("start"));
+        assert!(red.line().unwrap().starts_with("go"));
+        red.send(&"move 1").unwrap();
+        assert_eq!(red.line().unwrap(), "moved 4");
+
+        assert!(red.line().unwrap().starts_with("go"));
+        red.send(&"resign").unwrap();
+        assert_eq!(red.line().unwrap(), "result loss resignation");
+        assert_eq!(yellow.join().unwrap(), Ok(()));
+    }
+
+    #[test]
+    fn refused_clients_are_reported() {
+        let server = Server::bind("127.0.0.1:0", Clock::new(Duration::from_secs(5), Duration::ZERO), log_path("refused")).unwrap();
+        let address = server.local_addr().unwrap();
+        let (sender, reports) = std::sync::mpsc::channel();
+        std::thread::spawn(move || server.run(move |error| { let _ = sender.send(error); }));
+
+        let mut connection = Connection::new(TcpStream::connect(address).unwrap()).unwrap();
+        connection.send(&"join 0 old").unwrap();
+        assert_eq!(connection.line().unwrap(), format!("refused protocol version 0 is not {VERSION}"));
+
+        let report = reports.recv_timeout(HANDSHAKE).unwrap();
+        assert_eq!(report, format!("refused a client: protocol version 0 is not {VERSION}"));
+    }
+
+    #[test]
+    fn illegal_moves_are_refused() {
+        let log = log_path("illegal");
+        let server = Server::bind("127.0.0.1:0", Clock::new(Duration::from_secs(5), Duration::ZERO), &log).unwrap();
+        let address = server.local_addr().unwrap();
+        std::thread::spawn(move || server.run(|_| ()));
+
+        let mut red = raw_client(address, "red");
+        let mut yellow = raw_client(address, "yellow");
+
+        assert!(red.line().unwrap().starts_with("start"));
+        assert!(yellow.line().unwrap().starts_with("start"));
+
+        let turn = |player: &mut Connection, opponent: &mut Connection| {
+            assert!(player.line().unwrap().starts_with("go"));
+            player.send(&"move 4").unwrap();
+            assert_eq!(opponent.line().unwrap(), "moved 4");
+        };
+
+        // fill the fourth column, then red tries it once more
+        for _ in 0..Game::ROW / 2 {
+            turn(&mut red, &mut yellow);
+            turn(&mut yellow, &mut red);
+        }
+
+        assert!(red.line().unwrap().starts_with("go"));
+        red.send(&"move 4").unwrap();
+        assert_eq!(red.line().unwrap(), "illegal 4");
+
+        red.send(&"resign").unwrap();
+        assert_eq!(red.line().unwrap(), "result loss resignation");
+        assert_eq!(yellow.line().unwrap(), "result win resignation");
+
+        let entries = std::fs::read_to_string(&log).unwrap();
+        assert!(entries.trim_end().ends_with("red yellow yellow resignation 444444"));
+    }
+}
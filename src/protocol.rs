@@ -0,0 +1,324 @@
+//! Line protocol spoken over stdin/stdout, in the spirit of UCI. Columns
+//! are 1-based, positions are written as the list of played columns.
+//!
+//! ```text
+//! > isready                         < readyok
+//! > position startpos moves 4453
+//! > position board ......./......./......./......./...y.../...r... r
+//! > go movetime 500                 < bestmove 4
+//! > stop
+//! > eval                            < info score 1:-0.20 2:0.05 ...
+//! > quit
+//! ```
+//!
+//! A board lists the rows from top to bottom with `r`, `y` and `.` cells,
+//! followed by the player to move.
+//!
+//! An engine giving up the game replies `bestmove resign`, and `bestmove
+//! none` when the position is over.
+//!
+//! Errors are reported as `info string <message>` and never end the session.
+
+use crate::behaviour::{Behaviour, Intent, RESIGN};
+use crate::board_game::{Game, Player};
+
+use std::io::{BufRead, BufReader, Write};
+use std::sync::mpsc::{self, RecvTimeoutError};
+use std::time::Duration;
+
+pub const NAME: &str = "connect-four";
+
+pub enum Command {
+    IsReady,
+    Position(String),
+    Board(Game),
+    Go(Option<Duration>),
+    Stop,
+    Eval,
+    Quit,
+}
+
+impl std::str::FromStr for Command {
+    type Err = String;
+
+    fn from_str(line: &str) -> Result<Self, Self::Err> {
+        let words = line.split_whitespace().collect::<Vec<_>>();
+
+        match words.as_slice() {
+            ["isready"] => Ok(Command::IsReady),
+            ["position", "startpos"] | ["position", "startpos", "moves"] => Ok(Command::Position(String::new())),
+            ["position", "startpos", "moves", moves] => Ok(Command::Position(moves.to_string())),
+            ["position", "board", rows, turn] => Ok(Command::Board(decode_board(rows, turn)?)),
+            ["go"] => Ok(Command::Go(None)),
+            ["go", "movetime", time] => {
+                let millis = time.parse().map_err(|_| format!("invalid movetime '{time}'"))?;
+                Ok(Command::Go(Some(Duration::from_millis(millis))))
+            }
+            ["stop"] => Ok(Command::Stop),
+            ["eval"] => Ok(Command::Eval),
+            ["quit"] => Ok(Command::Quit),
+            _ => Err(format!("unknown command '{line}'")),
+        }
+    }
+}
+
+impl std::fmt::Display for Command {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Command::IsReady => write!(f, "isready"),
+            Command::Position(moves) if moves.is_empty() => write!(f, "position startpos"),
+            Command::Position(moves) => write!(f, "position startpos moves {moves}"),
+            Command::Board(game) => write!(f, "position board {}", encode_board(game)),
+            Command::Go(None) => write!(f, "go"),
+            Command::Go(Some(time)) => write!(f, "go movetime {}", time.as_millis()),
+            Command::Stop => write!(f, "stop"),
+            Command::Eval => write!(f, "eval"),
+            Command::Quit => write!(f, "quit"),
+        }
+    }
+}
+
+/// Serve `engine` on stdin/stdout until `quit` or the end of the input.
+pub fn run(engine: Box<dyn Behaviour>) -> Result<(), String> {
+    serve(engine, BufReader::new(std::io::stdin()), std::io::stdout())
+}
+
+/// Serve `engine` on `input` and `output` until `quit` or the end of the
+/// input.
+fn serve(mut engine: Box<dyn Behaviour>, input: impl BufRead + Send + 'static, mut output: impl Write) -> Result<(), String> {
+    let (sender, receiver) = mpsc::channel();
+
+    // the input blocks, read it aside so a running search can be polled
+    std::thread::spawn(move || {
+        for line in input.lines() {
+            let Ok(line) = line else { break; };
+            if sender.send(line).is_err() { break; }
+        }
+    });
+
+    let mut reply = |line: String| {
+        writeln!(output, "{line}").and_then(|_| output.flush()).map_err(|error| error.to_string())
+    };
+
+    reply(format!("id name {NAME}"))?;
+
+    let mut game = Game::default();
+    let mut search: Option<std::thread::JoinHandle<u8>> = None;
+
+    loop {
+        if search.as_ref().is_some_and(|handle| handle.is_finished()) {
+            let played = search.take().and_then(|handle| handle.join().ok());
+
+            if let Some(error) = engine.error() {
+                reply(format!("info string {error}"))?;
+            }
+
+            match played {
+                Some(RESIGN) => reply(String::from("bestmove resign"))?,
+                Some(col) => reply(format!("bestmove {}", col + 1))?,
+                None => reply(String::from("info string search crashed"))?,
+            }
+        }
+
+        let line = match receiver.recv_timeout(Duration::from_millis(5)) {
+            Ok(line) => line,
+            Err(RecvTimeoutError::Timeout) => continue,
+            Err(RecvTimeoutError::Disconnected) if search.is_some() => {
+                std::thread::sleep(Duration::from_millis(5));
+                continue;
+            }
+            Err(RecvTimeoutError::Disconnected) => return Ok(()),
+        };
+
+        if line.trim().is_empty() {
+            continue;
+        }
+
+        let command = match line.parse() {
+            Ok(command) => command,
+            Err(error) => {
+                reply(format!("info string {error}"))?;
+                continue;
+            }
+        };
+
+        match command {
+            Command::IsReady => reply(String::from("readyok"))?,
+            Command::Position(moves) => {
+                let mut position = Game::default();
+
+                match position.play_notation(&moves) {
+                    Ok(()) => game = position,
+                    Err(error) => reply(format!("info string {error}"))?,
+                }
+            }
+            Command::Board(position) => game = position,
+            Command::Go(_) if search.is_some() => reply(String::from("info string already searching"))?,
+            Command::Go(_) if over(&game) => reply(String::from("bestmove none"))?,
+            Command::Go(move_time) => {
+                if let Some(budget) = move_time {
+                    engine.move_time(budget);
+                }
+
+                engine.start_process(game);
+                let intent = engine.intent();
+
+                if let Some(error) = engine.error() {
+                    reply(format!("info string {error}"))?;
+                }
+
+                match intent {
+                    Intent::Some(RESIGN) => reply(String::from("bestmove resign"))?,
+                    Intent::Some(col) => reply(format!("bestmove {}", col + 1))?,
+                    Intent::Waiting(handle) => search = Some(handle),
+                    Intent::None => reply(String::from("bestmove none"))?,
+                }
+            }
+            Command::Stop => engine.stop(),
+            Command::Eval => match engine.evaluate(game) {
+                Some(scores) => {
+                    let scores = scores.iter()
+                        .enumerate()
+                        .map(|(col, score)| format!("{}:{score:.2}", col + 1))
+                        .collect::<Vec<_>>()
+                        .join(" ");
+
+                    reply(format!("info score {scores}"))?;
+                }
+                None => reply(String::from("info string engine cannot evaluate"))?,
+            },
+            Command::Quit => {
+                engine.stop();
+                return Ok(());
+            }
+        }
+    }
+}
+
+/// No move is left or a line is already on the board.
+fn over(game: &Game) -> bool {
+    game.legal_moves().is_empty() || (0..game.cols()).any(|col| (0..game.rows()).any(|row| game.connected(col, row)))
+}
+
+/// Rows from top to bottom followed by the player to move, as in
+/// `......./......./......./......./...y.../...r... r`.
+pub fn encode_board(game: &Game) -> String {
+    let grid = game.grid();
+
+    let rows = (0..game.rows()).rev()
+        .map(|row| {
+            (0..game.cols())
+                .map(|col| match grid[col * game.rows() + row] {
+                    Some(Player::Red) => 'r',
+                    Some(Player::Yellow) => 'y',
+                    Some(Player::Green) => 'g',
+                    Some(Player::Blue) => 'b',
+                    None => '.',
+                })
+                .collect::<String>()
+        })
+        .collect::<Vec<_>>()
+        .join("/");
+
+    let turn = match game.player_turn() {
+        Player::Red => 'r',
+        Player::Yellow => 'y',
+        Player::Green => 'g',
+        Player::Blue => 'b',
+    };
+
+    format!("{rows} {turn}")
+}
+
+pub fn decode_board(rows: &str, turn: &str) -> Result<Game, String> {
+    let rows = rows.split('/').collect::<Vec<_>>();
+    if rows.len() != Game::ROW || rows.iter().any(|row| row.chars().count() != Game::COL) {
+        return Err(format!("a board has {} rows of {} cells", Game::ROW, Game::COL));
+    }
+
+    let mut grid = [None; Game::ROW * Game::COL];
+
+    for (index, row) in rows.iter().enumerate() {
+        let row_index = Game::ROW - index - 1;
+
+        for (col, cell) in row.chars().enumerate() {
+            grid[col * Game::ROW + row_index] = match cell {
+                'r' => Some(Player::Red),
+                'y' => Some(Player::Yellow),
+                '.' => None,
+                _ => return Err(format!("invalid cell '{cell}'")),
+            };
+        }
+    }
+
+    let turn = match turn {
+        "r" => Player::Red,
+        "y" => Player::Yellow,
+        _ => return Err(format!("invalid player to move '{turn}'")),
+    };
+
+    Game::from_grid(grid, turn)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::behaviour::Rollout;
+
+    /// Full grid without any line: pairs of columns alternate in every row.
+    const DRAWN: &str = "yyrryyr/rryyrry/yyrryyr/rryyrry/yyrryyr/rryyrry";
+
+    #[test]
+    fn commands_are_written_as_they_are_read() {
+        let lines = [
+            "isready", "position startpos", "position startpos moves 4453",
+            "position board ......./......./......./......./...y.../...r... r",
+            "go", "go movetime 500", "stop", "eval", "quit",
+        ];
+
+        for line in lines {
+            assert_eq!(line.parse::<Command>().unwrap().to_string(), line);
+        }
+
+        assert_eq!("position startpos moves".parse::<Command>().unwrap().to_string(), "position startpos");
+        assert!("go movetime soon".parse::<Command>().is_err());
+        assert!("position".parse::<Command>().is_err());
+    }
+
+    #[test]
+    fn boards_are_decoded_as_they_are_encoded() {
+        let mut game = Game::default();
+        game.play_notation("4453").unwrap();
+
+        let board = encode_board(&game);
+        assert_eq!(board, "......./......./......./......./...y.../..yrr.. r");
+
+        let (rows, turn) = board.split_once(' ').unwrap();
+        let decoded = decode_board(rows, turn).unwrap();
+        assert_eq!(decoded.grid(), game.grid());
+        assert_eq!(decoded.player_turn(), game.player_turn());
+
+        assert!(decode_board("......./......./......./...y...", "r").is_err());
+        assert!(decode_board("......./......./......./......./...x.../...r...", "r").is_err());
+        assert!(decode_board("......./......./......./...y.../......./...r...", "r").is_err());
+        assert!(decode_board("......./......./......./......./......./...r...", "g").is_err());
+        assert!(decode_board(DRAWN, "r").is_ok());
+    }
+
+    #[test]
+    fn a_session_searches_and_replies() {
+        let input = format!("isready\nposition board {DRAWN} r\ngo\nfly\nposition startpos moves\nposition startpos moves 112233\ngo\n");
+        let mut output = Vec::new();
+
+        serve(Box::new(Rollout::new(20, 3)), std::io::Cursor::new(input), &mut output).unwrap();
+
+        let output = String::from_utf8(output).unwrap();
+        assert_eq!(output.lines().collect::<Vec<_>>(), [
+            "id name connect-four",
+            "readyok",
+            "bestmove none",
+            "info string unknown command 'fly'",
+            "bestmove 4",
+        ]);
+    }
+}
@@ -0,0 +1,206 @@
+use crate::board_game::{move_column, Game, PlayResult};
+
+use std::collections::HashMap;
+
+/// Score of a position won by the player to move on their next move,
+/// one less for every further move it takes.
+pub const WIN: i16 = 1000;
+
+/// Scores this close to `WIN` are forced wins or losses.
+pub const MATE: i16 = WIN - 200;
+
+#[derive(Clone, Copy)]
+enum Bound {
+    Exact, Lower, Upper,
+}
+
+#[derive(Clone, Copy)]
+struct Entry {
+    depth: u8,
+    score: i16,
+    bound: Bound,
+}
+
+/// Depth limited alpha-beta search of two player games, positions past
+/// the depth scoring as draws unless an evaluation is given. Positions
+/// are kept in a transposition table shared by a position and its
+/// mirror.
+#[derive(Default)]
+pub struct Search {
+    table: HashMap<u64, Entry>,
+    nodes: u64,
+    evaluation: Option<fn(&Game) -> i16>,
+}
+
+impl Search {
+    /// Score positions past the depth with `evaluation`, for the player
+    /// to move and short of `MATE`, such as `analysis::evaluate`.
+    pub fn evaluation(mut self, evaluation: fn(&Game) -> i16) -> Self {
+        self.evaluation = Some(evaluation);
+        self
+    }
+
+    /// Best move of the player to move looking `depth` moves ahead, with
+    /// its score: positive when winning, see `WIN`. `None` when no move
+    /// is left.
+    pub fn best_move(&mut self, game: &Game, depth: u8) -> Option<(u8, i16)> {
+        let mut best: Option<(u8, i16)> = None;
+
+        for code in ordered_moves(game) {
+            let alpha = best.map_or(-WIN, |(_, score)| score);
+            let score = self.child(game, code, depth, alpha, WIN, 0);
+
+            if best.is_none_or(|(_, best)| score > best) {
+                best = Some((code, score));
+            }
+        }
+
+        best
+    }
+
+    /// Positions searched since the search was created.
+    pub fn nodes(&self) -> u64 {
+        self.nodes
+    }
+
+    fn negamax(&mut self, game: &Game, depth: u8, mut alpha: i16, mut beta: i16, ply: i16) -> i16 {
+        self.nodes += 1;
+
+        let moves = ordered_moves(game);
+        if moves.is_empty() {
+            return 0;
+        }
+
+        if depth == 0 {
+            return self.evaluation.map_or(0, |evaluation| evaluation(game));
+        }
+
+        let key = key(game);
+        let original = (alpha, beta);
+
+        if let Some(entry) = self.table.get(&key).filter(|entry| entry.depth >= depth) {
+            let score = from_table(entry.score, ply);
+
+            match entry.bound {
+                Bound::Exact => return score,
+                Bound::Lower => alpha = alpha.max(score),
+                Bound::Upper => beta = beta.min(score),
+            }
+
+            if alpha >= beta {
+                return score;
+            }
+        }
+
+        let mut best = -WIN;
+
+        for code in moves {
+            best = best.max(self.child(game, code, depth, alpha, beta, ply));
+            alpha = alpha.max(best);
+
+            if alpha >= beta {
+                break;
+            }
+        }
+
+        let bound = if best <= original.0 {
+            Bound::Upper
+        } else if best >= original.1 {
+            Bound::Lower
+        } else {
+            Bound::Exact
+        };
+
+        self.table.insert(key, Entry { depth, score: to_table(best, ply), bound });
+        best
+    }
+
+    /// Score of `code` for the player to move. Some variants let the same
+    /// player move again, their score is then not negated.
+    fn child(&mut self, game: &Game, code: u8, depth: u8, alpha: i16, beta: i16, ply: i16) -> i16 {
+        let player = game.player_turn();
+        let mut child = *game;
+
+        match child.play(code) {
+            PlayResult::Win(_) => WIN - ply,
+            PlayResult::Lose(_) => ply - WIN,
+            PlayResult::Error => -WIN,
+            PlayResult::Pass if child.player_turn() == player => self.negamax(&child, depth.saturating_sub(1), alpha, beta, ply + 1),
+            PlayResult::Pass => -self.negamax(&child, depth.saturating_sub(1), -beta, -alpha, ply + 1),
+        }
+    }
+}
+
+/// Hash of the canonical position with the variant tally, stable from
+/// one run to the next so that it can be stored.
+pub fn key(game: &Game) -> u64 {
+    let (canonical, _) = game.canonical();
+    let tally = canonical.tally();
+
+    let bytes = tally.captured.into_iter()
+        .chain(tally.powers)
+        .chain([tally.pending.unwrap_or(u8::MAX), tally.filling as u8]);
+
+    // FNV-1a
+    let tally = bytes.fold(0xcbf2_9ce4_8422_2325, |hash: u64, byte| {
+        (hash ^ byte as u64).wrapping_mul(0x0100_0000_01b3)
+    });
+
+    canonical.zobrist() ^ tally
+}
+
+/// Legal moves from the centre outwards, the best ones first most of the
+/// time.
+fn ordered_moves(game: &Game) -> Vec<u8> {
+    let mut moves = game.legal_moves().collect::<Vec<_>>();
+    moves.sort_by_key(|&code| move_column(code).abs_diff(game.cols() / 2));
+    moves
+}
+
+/// Win scores are stored relative to the position, not to the root.
+fn to_table(score: i16, ply: i16) -> i16 {
+    match score {
+        score if score > MATE => score + ply,
+        score if score < -MATE => score - ply,
+        score => score,
+    }
+}
+
+fn from_table(score: i16, ply: i16) -> i16 {
+    match score {
+        score if score > MATE => score - ply,
+        score if score < -MATE => score + ply,
+        score => score,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn wins_are_found_and_threats_blocked() {
+        let mut game = Game::default();
+        game.play_notation("112233").unwrap();
+        assert_eq!(Search::default().best_move(&game, 2), Some((3, WIN)));
+
+        game.play_notation("7").unwrap();
+        assert_eq!(Search::default().best_move(&game, 4).map(|(code, _)| code), Some(3));
+
+        // an open three on the bottom row cannot be stopped
+        let mut game = Game::default();
+        game.play_notation("33435").unwrap();
+        assert_eq!(Search::default().best_move(&game, 3).map(|(_, score)| score), Some(1 - WIN));
+    }
+
+    #[test]
+    fn evaluations_score_the_positions_past_the_depth() {
+        let mut game = Game::default();
+        game.play_notation("4").unwrap();
+
+        // coins in the middle column are good for the player to move
+        // next, the nearest other column is played instead
+        let mut search = Search::default().evaluation(|game| game.col_height(3) as i16);
+        assert_eq!(search.best_move(&game, 1), Some((2, -1)));
+    }
+}
@@ -0,0 +1,310 @@
+use crate::behaviour::{Behaviour, Intent, RESIGN};
+use crate::board_game::{move_notation, parse_move, Game, PlayResult, Player, Score};
+use crate::cli::Options;
+
+use std::io::{BufRead, Write};
+
+const RED: &str = "\x1b[1;31m●\x1b[0m";
+const YELLOW: &str = "\x1b[1;33m●\x1b[0m";
+const GREEN: &str = "\x1b[1;32m●\x1b[0m";
+const BLUE: &str = "\x1b[1;34m●\x1b[0m";
+const EMPTY: &str = "\x1b[2m·\x1b[0m";
+
+enum State {
+    Playing,
+    Won(Player),
+    Draw,
+    /// A player forfeited a game between more than two players, the
+    /// others all win.
+    Resigned(Player),
+}
+
+/// Interactive game in the terminal. Human seats type a column number,
+/// preceded by a letter for special moves such as `p4`, other seats are
+/// driven through their intents like on the iced board.
+pub fn run(options: &Options) -> Result<(), String> {
+    let mut seats = options.players.iter()
+        .map(|&player| (player, options.seat(player).behaviour()))
+        .collect::<Vec<_>>();
+
+    let mut score = options.best_of.map(Score::best_of).unwrap_or_default();
+    let mut game = options.game()?;
+    let mut history = Vec::new();
+    let mut state = State::Playing;
+
+    let mut lines = std::io::stdin().lock().lines();
+    let mut message = String::new();
+
+    loop {
+        render(&game, &score, &state, &message);
+        message.clear();
+
+        let player = game.player_turn();
+        let behaviour = seat(&mut seats, player);
+
+        let automatic = matches!(state, State::Playing) && !behaviour.process_intent();
+
+        let input = if automatic {
+            behaviour.start_process(game);
+            let code = wait_intent(behaviour)?;
+
+            if let Some(error) = behaviour.error() {
+                message = format!("{}: {error}", player.name());
+            }
+
+            match code {
+                RESIGN => Input::Resign,
+                code => Input::Play(code),
+            }
+        } else {
+            print!("{} > ", prompt(&game, &state, player));
+            std::io::stdout().flush().map_err(|error| error.to_string())?;
+
+            let Some(line) = lines.next() else {
+                return Ok(());
+            };
+
+            Input::parse(&line.map_err(|error| error.to_string())?)
+        };
+
+        match input {
+            Input::Play(code) if matches!(state, State::Playing) => {
+                let before = game;
+
+                match game.play(code) {
+                    // asking again would get the same move forever
+                    PlayResult::Error if automatic => {
+                        message = format!("{} played the illegal move {} and forfeits", player.name(), move_notation(code));
+                        state = forfeit(&game, player, &mut score, &mut seats);
+                    }
+                    PlayResult::Error => message = format!("{} is not a legal move", move_notation(code)),
+                    result => {
+                        history.push(before);
+
+                        for (seat, behaviour) in &mut seats {
+                            if *seat != player {
+                                behaviour.opponent_played(code, game);
+                            }
+                        }
+
+                        if let PlayResult::Win(_) = result {
+                            score.record_win(player);
+                            state = State::Won(player);
+                        } else if let PlayResult::Lose(lines) = result {
+                            score.record_win(lines[0].player());
+                            state = State::Won(lines[0].player());
+                        } else if game.stalemate() {
+                            score.record_draw();
+                            state = State::Draw;
+                        }
+                    }
+                }
+            }
+            Input::Play(_) => message = String::from("the game is over, restart or quit"),
+            Input::Resign if matches!(state, State::Playing) => state = forfeit(&game, player, &mut score, &mut seats),
+            Input::Undo if matches!(state, State::Playing) && seats.iter().all(|(_, behaviour)| behaviour.undoable()) => {
+                // go back to the last position where a human was to play
+                let mut kept = history.len();
+
+                loop {
+                    let Some(previous) = kept.checked_sub(1) else {
+                        message = String::from("nothing to undo");
+                        break;
+                    };
+
+                    kept = previous;
+                    let undone = history[kept];
+
+                    if seat(&mut seats, undone.player_turn()).process_intent() {
+                        game = undone;
+                        history.truncate(kept);
+                        break;
+                    }
+                }
+            }
+            Input::Undo | Input::Resign => message = String::from("not now"),
+            Input::Restart => {
+                if score.match_over() {
+                    score.reset();
+                }
+
+                game.restart();
+                history.clear();
+                state = State::Playing;
+
+                for (_, behaviour) in &mut seats {
+                    behaviour.new_game();
+                }
+            }
+            Input::Quit => return Ok(()),
+            Input::Unknown => message = String::from("type a column from 1 to 7, preceded by a letter for a special move, resign, u, r or q"),
+        }
+    }
+}
+
+/// `player` resigned or played an illegal move: every other player wins.
+fn forfeit(game: &Game, player: Player, score: &mut Score, seats: &mut [(Player, Box<dyn Behaviour>)]) -> State {
+    for (seat, behaviour) in seats.iter_mut() {
+        if *seat != player {
+            behaviour.opponent_resigned();
+        }
+    }
+
+    match game.opponent_of(player) {
+        Some(winner) => {
+            score.record_win(winner);
+            State::Won(winner)
+        }
+        None => {
+            score.record_forfeit(player, game.players());
+            State::Resigned(player)
+        }
+    }
+}
+
+#[derive(PartialEq, Eq, Debug)]
+enum Input {
+    Play(u8),
+    Resign,
+    Undo,
+    Restart,
+    Quit,
+    Unknown,
+}
+
+impl Input {
+    fn parse(line: &str) -> Self {
+        match line.trim() {
+            "u" | "undo" => Input::Undo,
+            "resign" => Input::Resign,
+            "r" | "restart" => Input::Restart,
+            "q" | "quit" => Input::Quit,
+            text => match parse_move(text) {
+                Some(code) => Input::Play(code),
+                None => Input::Unknown,
+            },
+        }
+    }
+}
+
+fn seat(seats: &mut [(Player, Box<dyn Behaviour>)], player: Player) -> &mut dyn Behaviour {
+    seats.iter_mut()
+        .find(|(seat, _)| *seat == player)
+        .map(|(_, behaviour)| behaviour.as_mut())
+        .expect("every player of the game is seated")
+}
+
+fn wait_intent(behaviour: &mut dyn Behaviour) -> Result<u8, String> {
+    match behaviour.intent() {
+        Intent::Some(col) => Ok(col),
+        Intent::Waiting(handle) => handle.join().map_err(|_| String::from("behaviour crashed")),
+        Intent::None => Err(String::from("behaviour did not play")),
+    }
+}
+
+fn prompt(game: &Game, state: &State, player: Player) -> String {
+    match state {
+        State::Playing => format!("{} to play [1-{}, u, r, q]", glyph(player), game.cols()),
+        State::Won(winner) => format!("{} wins [r, q]", glyph(*winner)),
+        State::Draw => String::from("draw [r, q]"),
+        State::Resigned(player) => format!("{} forfeits, the others win [r, q]", glyph(*player)),
+    }
+}
+
+fn render(game: &Game, score: &Score, state: &State, message: &str) {
+    let side = game.rules().side_columns() as i8;
+    let mut screen = String::from("\x1b[2J\x1b[H");
+
+    screen += &match game.players() {
+        &[first, second] => format!(
+            "{} {} - {} {}    draws {}\n\n",
+            glyph(first), score.wins(first), score.wins(second), glyph(second), score.draws(),
+        ),
+        players => {
+            let wins = players.iter().map(|&player| format!("{} {}", glyph(player), score.wins(player)));
+            format!("{}    draws {}\n\n", wins.collect::<Vec<_>>().join("  "), score.draws())
+        }
+    };
+
+    for row in (0..game.rows()).rev() {
+        screen += "  |";
+
+        for col in -side..game.cols() as i8 + side {
+            let cell = game.cell(col, row).map_or(EMPTY, glyph);
+
+            screen += &format!(" {cell}");
+        }
+
+        screen += " |\n";
+    }
+
+    screen += &"  ".repeat(side as usize + 1);
+    screen += " ";
+    for col in 1..=game.cols() {
+        screen += &format!(" {col}");
+    }
+
+    screen += "\n\n";
+
+    if let Some(status) = game.rules().status(game) {
+        screen += &format!("{status}\n");
+    }
+
+    if let State::Draw = state {
+        screen += "no move is left\n";
+    }
+
+    if !message.is_empty() {
+        screen += &format!("{message}\n");
+    }
+
+    print!("{screen}");
+}
+
+fn glyph(player: Player) -> &'static str {
+    match player {
+        Player::Red => RED,
+        Player::Yellow => YELLOW,
+        Player::Green => GREEN,
+        Player::Blue => BLUE,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::behaviour::Random;
+    use crate::board_game::{ANVIL, POP};
+
+    #[test]
+    fn inputs_are_parsed() {
+        assert_eq!(Input::parse("4"), Input::Play(3));
+        assert_eq!(Input::parse(" p4 \n"), Input::Play(3 | POP));
+        assert_eq!(Input::parse("a7"), Input::Play(6 | ANVIL));
+        assert_eq!(Input::parse("u"), Input::Undo);
+        assert_eq!(Input::parse("undo"), Input::Undo);
+        assert_eq!(Input::parse("resign"), Input::Resign);
+        assert_eq!(Input::parse("r"), Input::Restart);
+        assert_eq!(Input::parse("quit"), Input::Quit);
+
+        for unknown in ["", "0", "10", "x4", "play"] {
+            assert_eq!(Input::parse(unknown), Input::Unknown);
+        }
+    }
+
+    #[test]
+    fn a_forfeit_wins_for_every_other_player() {
+        let mut seats = Player::ALL.map(|player| (player, Box::<Random>::default() as Box<dyn Behaviour>));
+        let mut score = Score::default();
+
+        let state = forfeit(&Game::default(), Player::Red, &mut score, &mut seats);
+        assert!(matches!(state, State::Won(Player::Yellow)));
+        assert_eq!(score.wins(Player::Yellow), 1);
+
+        let game = Game::new(Player::Red).seating(&[Player::Red, Player::Green, Player::Yellow]);
+        let state = forfeit(&game, Player::Green, &mut score, &mut seats);
+        assert!(matches!(state, State::Resigned(Player::Green)));
+        assert_eq!((score.wins(Player::Red), score.wins(Player::Yellow), score.wins(Player::Green)), (1, 2, 0));
+        assert_eq!((score.played(), score.draws()), (2, 0));
+    }
+}
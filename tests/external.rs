@@ -0,0 +1,75 @@
+//! `External` driving this crate's own binary served with `--engine`.
+
+use connect_four::behaviour::{Behaviour, External, Intent};
+use connect_four::board_game::Game;
+
+use std::time::{Duration, Instant};
+
+fn engine(seat: &str) -> External {
+    External::new(env!("CARGO_BIN_EXE_connect-four"), vec![String::from("--engine"), String::from(seat)])
+}
+
+/// Red to move wins in column 4.
+fn winning() -> Game {
+    let mut game = Game::default();
+    game.play_notation("112233").unwrap();
+    game
+}
+
+fn wait(external: &mut External) -> u8 {
+    match external.intent() {
+        Intent::Some(col) => col,
+        Intent::Waiting(handle) => handle.join().unwrap(),
+        Intent::None => panic!("the engine did not play"),
+    }
+}
+
+#[test]
+fn the_engine_plays_its_move() {
+    let mut external = engine("rollout:50:3");
+
+    // the second move is asked to the same process
+    for _ in 0..2 {
+        external.start_process(winning());
+        assert_eq!(wait(&mut external), 3);
+        assert_eq!(external.error(), None);
+    }
+}
+
+#[test]
+fn a_slow_engine_is_stopped_on_timeout() {
+    let mut external = engine("rollout:60000:42").timeout(Duration::from_millis(300));
+    let start = Instant::now();
+
+    external.start_process(winning());
+    assert_eq!(wait(&mut external), 3);
+    assert!(start.elapsed() < Duration::from_secs(5));
+    assert_eq!(external.error(), None);
+}
+
+#[test]
+fn stop_does_not_wait_for_the_search() {
+    let mut external = engine("rollout:60000:42").timeout(Duration::from_secs(60));
+
+    external.start_process(winning());
+    std::thread::sleep(Duration::from_millis(200));
+
+    let start = Instant::now();
+    external.stop();
+    assert!(start.elapsed() < Duration::from_millis(50));
+
+    assert_eq!(wait(&mut external), 3);
+    assert!(start.elapsed() < Duration::from_secs(5));
+    assert_eq!(external.error(), None);
+}
+
+#[test]
+fn a_dead_engine_is_replaced_by_a_random_move() {
+    let mut external = engine("nobody");
+
+    external.start_process(winning());
+    assert!(winning().legal_moves().contains(wait(&mut external)));
+
+    let error = external.error().unwrap();
+    assert!(error.contains("exited"), "{error}");
+}